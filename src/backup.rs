@@ -0,0 +1,313 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{NaiveDateTime, Local};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use crate::config::{backup_dir, RetentionPolicy};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H%M%S";
+
+#[derive(Debug)]
+pub enum BackupError {
+    Sqlite(rusqlite::Error),
+    Io(std::io::Error),
+    /// `PRAGMA integrity_check` did not answer `ok` for this file.
+    Corrupt(PathBuf, String),
+    NotFound(String),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Sqlite(err) => write!(f, "backup failed: {}", err),
+            BackupError::Io(err) => write!(f, "backup failed: {}", err),
+            BackupError::Corrupt(path, report) => {
+                write!(f, "integrity check failed for {}: {}", path.display(), report)
+            }
+            BackupError::NotFound(choice) => write!(f, "no backup matches '{}'", choice),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(err: rusqlite::Error) -> Self {
+        BackupError::Sqlite(err)
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(err: std::io::Error) -> Self {
+        BackupError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub taken_at: NaiveDateTime,
+}
+
+/// Runs `PRAGMA integrity_check`, returning the report when it is not `ok`.
+fn check_integrity(path: &Path) -> Result<(), BackupError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let report = conn
+        .prepare("PRAGMA integrity_check")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()
+        .map_err(|err| BackupError::Corrupt(path.to_path_buf(), err.to_string()))?
+        .join("; ");
+    if report == "ok" {
+        Ok(())
+    } else {
+        Err(BackupError::Corrupt(path.to_path_buf(), report))
+    }
+}
+
+/// Copies `src` into `dst` page by page with SQLite's online backup API.
+fn copy_database(src: &Path, dst: &Path) -> Result<(), BackupError> {
+    let src = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut dst = Connection::open(dst)?;
+    Backup::new(&src, &mut dst)?.run_to_completion(256, Duration::ZERO, None)?;
+    Ok(())
+}
+
+fn backup_file_name(db_path: &Path, taken_at: NaiveDateTime) -> String {
+    format!("{}_{}.sqlite3", db_stem(db_path), taken_at.format(TIMESTAMP_FORMAT))
+}
+
+fn db_stem(db_path: &Path) -> &str {
+    db_path.file_stem().and_then(|s| s.to_str()).unwrap_or("buldak")
+}
+
+/// Takes a timestamped backup of a database that passes the integrity check.
+/// A corrupted live file is refused so it can never push out a good backup.
+pub fn create_backup(db_path: &Path) -> Result<PathBuf, BackupError> {
+    check_integrity(db_path)?;
+    let dir = backup_dir(db_path);
+    fs::create_dir_all(&dir)?;
+    let target = dir.join(backup_file_name(db_path, Local::now().naive_local()));
+    copy_database(db_path, &target)?;
+    if let Err(err) = check_integrity(&target) {
+        let _ = fs::remove_file(&target);
+        return Err(err);
+    }
+    Ok(target)
+}
+
+/// Backups of `db_path`, newest first.
+pub fn list_backups(db_path: &Path) -> Result<Vec<BackupFile>, BackupError> {
+    let dir = backup_dir(db_path);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let prefix = format!("{}_", db_stem(db_path));
+    let mut backups = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let timestamp = name.strip_prefix(&prefix)?.strip_suffix(".sqlite3")?;
+            let taken_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+            Some(BackupFile { path, taken_at })
+        })
+        .collect::<Vec<BackupFile>>();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.taken_at));
+    Ok(backups)
+}
+
+/// Picks the backups a policy keeps from a newest-first list.
+fn backups_to_keep(backups: &[BackupFile], policy: RetentionPolicy) -> HashSet<PathBuf> {
+    let mut keep = HashSet::new();
+    let mut newest_per = |period: &str, limit: usize| {
+        let mut seen = Vec::new();
+        for backup in backups {
+            let key = backup.taken_at.format(period).to_string();
+            if !seen.contains(&key) {
+                if seen.len() == limit {
+                    break;
+                }
+                seen.push(key);
+                keep.insert(backup.path.clone());
+            }
+        }
+    };
+    newest_per("%Y-%m-%d", policy.keep_daily);
+    newest_per("%Y-%m", policy.keep_monthly);
+    keep
+}
+
+/// Deletes backups outside the retention policy, returning what was removed.
+pub fn prune_backups(db_path: &Path, policy: RetentionPolicy) -> Result<Vec<PathBuf>, BackupError> {
+    let backups = list_backups(db_path)?;
+    let keep = backups_to_keep(&backups, policy);
+    let mut removed = Vec::new();
+    for backup in backups.into_iter().filter(|b| !keep.contains(&b.path)) {
+        fs::remove_file(&backup.path)?;
+        removed.push(backup.path);
+    }
+    Ok(removed)
+}
+
+/// Replaces the live database with a backup, after saving the current state as one more
+/// backup. Nothing is replaced when the current state can't be saved.
+pub fn restore_backup(db_path: &Path, backup: &Path) -> Result<(), BackupError> {
+    check_integrity(backup)?;
+    // read it first: the safety backup may get the name of a backup taken in the same second
+    let mut restored = Connection::open_in_memory()?;
+    {
+        let src = Connection::open_with_flags(backup, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Backup::new(&src, &mut restored)?.run_to_completion(256, Duration::ZERO, None)?;
+    }
+    if db_path.exists() {
+        save_before_restore(db_path)?;
+    }
+    let mut live = Connection::open(db_path)?;
+    Backup::new(&restored, &mut live)?.run_to_completion(256, Duration::ZERO, None)?;
+    Ok(())
+}
+
+/// Backs up the live database before a restore. A file that fails the integrity check is
+/// usually why it is restored; it is moved aside byte for byte as `<name>_<time>.corrupt`
+/// instead, since SQLite can't copy a backup over it.
+fn save_before_restore(db_path: &Path) -> Result<PathBuf, BackupError> {
+    if check_integrity(db_path).is_ok() {
+        return create_backup(db_path);
+    }
+    let dir = backup_dir(db_path);
+    fs::create_dir_all(&dir)?;
+    let target = dir.join(format!("{}_{}.corrupt", db_stem(db_path), Local::now().format(TIMESTAMP_FORMAT)));
+    fs::copy(db_path, &target)?;
+    fs::remove_file(db_path)?;
+    Ok(target)
+}
+
+/// Finds a backup by its number in [`list_backups`] (1 = newest) or by path.
+pub fn find_backup(db_path: &Path, choice: &str) -> Result<PathBuf, BackupError> {
+    let backups = list_backups(db_path)?;
+    if let Ok(number) = choice.parse::<usize>() {
+        return backups
+            .get(number.wrapping_sub(1))
+            .map(|b| b.path.clone())
+            .ok_or_else(|| BackupError::NotFound(choice.to_string()));
+    }
+    let path = PathBuf::from(choice);
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(BackupError::NotFound(choice.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn backup(date: &str, time: &str) -> BackupFile {
+        let taken_at = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap()
+            .and_time(time.parse().unwrap());
+        BackupFile { path: PathBuf::from(format!("{} {}", date, time)), taken_at }
+    }
+
+    #[test]
+    fn test_backups_to_keep_daily_and_monthly() {
+        // newest first, as returned by list_backups
+        let backups = vec![
+            backup("2025-03-14", "20:00:00"),
+            backup("2025-03-14", "08:00:00"),
+            backup("2025-03-13", "09:00:00"),
+            backup("2025-03-01", "09:00:00"),
+            backup("2025-02-20", "09:00:00"),
+            backup("2025-02-03", "09:00:00"),
+            backup("2025-01-10", "09:00:00"),
+        ];
+        let keep = backups_to_keep(&backups, RetentionPolicy { keep_daily: 2, keep_monthly: 2 });
+        let mut kept = keep.iter().map(|p| p.to_str().unwrap().to_string()).collect::<Vec<_>>();
+        kept.sort();
+        assert_eq!(kept, vec!["2025-02-20 09:00:00", "2025-03-13 09:00:00", "2025-03-14 20:00:00"]);
+    }
+
+    #[test]
+    fn test_backups_to_keep_nothing() {
+        let backups = vec![backup("2025-03-14", "20:00:00")];
+        assert!(backups_to_keep(&backups, RetentionPolicy { keep_daily: 0, keep_monthly: 0 }).is_empty());
+    }
+
+    /// Empty directory for one test, removed again by the test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("buldak-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn database(path: &Path, value: i64) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch("CREATE TABLE IF NOT EXISTS t (value INTEGER); DELETE FROM t;").unwrap();
+        conn.execute("INSERT INTO t (value) VALUES (?1)", [value]).unwrap();
+    }
+
+    fn value(path: &Path) -> i64 {
+        Connection::open(path).unwrap().query_row("SELECT value FROM t", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_backup_change_restore_round_trip() {
+        let dir = scratch_dir("round-trip");
+        let db_path = dir.join("buldak.sqlite3");
+        database(&db_path, 1);
+        let backup = create_backup(&db_path).unwrap();
+        database(&db_path, 2);
+
+        restore_backup(&db_path, &backup).unwrap();
+        assert_eq!(value(&db_path), 1);
+        assert!(list_backups(&db_path).unwrap().iter().any(|b| value(&b.path) == 2), "the changed state was saved first");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_refuses_corrupt_backup_and_failed_safety_backup() {
+        let dir = scratch_dir("restore-refused");
+        let db_path = dir.join("buldak.sqlite3");
+        database(&db_path, 1);
+        let corrupt = dir.join("corrupt.sqlite3");
+        fs::write(&corrupt, b"not a database at all, just some bytes").unwrap();
+        assert!(restore_backup(&db_path, &corrupt).is_err());
+        assert_eq!(value(&db_path), 1);
+
+        let good = dir.join("good.sqlite3");
+        database(&good, 2);
+        // the backups directory can't be created, so neither can the safety backup
+        fs::write(dir.join("backups"), b"").unwrap();
+        assert!(restore_backup(&db_path, &good).is_err());
+        assert_eq!(value(&db_path), 1, "nothing replaced without a safety backup");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_live_database_kept_as_is() {
+        let dir = scratch_dir("corrupt-live");
+        let db_path = dir.join("buldak.sqlite3");
+        let good = dir.join("good.sqlite3");
+        database(&good, 3);
+        fs::write(&db_path, b"garbage").unwrap();
+
+        restore_backup(&db_path, &good).unwrap();
+        assert_eq!(value(&db_path), 3);
+        let kept: Vec<_> = fs::read_dir(dir.join("backups")).unwrap().map(|e| e.unwrap().path()).collect();
+        assert!(kept.iter().any(|path| path.extension().is_some_and(|e| e == "corrupt") && fs::read(path).unwrap() == b"garbage"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_backup_file_name_round_trips() {
+        let taken_at = backup("2025-03-14", "20:01:02").taken_at;
+        let name = backup_file_name(Path::new("/data/buldak.sqlite3"), taken_at);
+        assert_eq!(name, "buldak_2025-03-14_200102.sqlite3");
+    }
+}
@@ -0,0 +1,50 @@
+use crate::money::Money;
+
+/// Share of the budget, in percent, from which a total is shown as nearing it.
+pub const NEAR_BUDGET_PERCENT: i64 = 80;
+
+/// How a total stands against its budget; drives the footer and chart colours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetLevel {
+    /// No budget set, or well below it.
+    Fine,
+    Near,
+    Over,
+}
+
+impl BudgetLevel {
+    pub fn of(spent: Money, budget: Option<Money>) -> Self {
+        let Some(budget) = budget.filter(|b| !b.is_zero() && !b.is_negative()) else {
+            return BudgetLevel::Fine;
+        };
+        if spent > budget {
+            BudgetLevel::Over
+        } else if spent.cents() as i128 * 100 >= budget.cents() as i128 * NEAR_BUDGET_PERCENT as i128 {
+            BudgetLevel::Near
+        } else {
+            BudgetLevel::Fine
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(BudgetLevel::of(m("500"), None), BudgetLevel::Fine);
+        assert_eq!(BudgetLevel::of(m("500"), Some(Money::ZERO)), BudgetLevel::Fine);
+        assert_eq!(BudgetLevel::of(m("79.99"), Some(m("100"))), BudgetLevel::Fine);
+        assert_eq!(BudgetLevel::of(m("80"), Some(m("100"))), BudgetLevel::Near);
+        assert_eq!(BudgetLevel::of(m("100"), Some(m("100"))), BudgetLevel::Near);
+        assert_eq!(BudgetLevel::of(m("100.01"), Some(m("100"))), BudgetLevel::Over);
+        // a year of spending past 9.2e14 € would overflow the percentage in i64
+        let huge = Money::from_cents(i64::MAX / 10);
+        assert_eq!(BudgetLevel::of(huge, Some(huge)), BudgetLevel::Near);
+    }
+}
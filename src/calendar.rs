@@ -0,0 +1,203 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Utc};
+use crossterm::event::{KeyCode, KeyEvent};
+use indexmap::IndexMap;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, Cell, Clear, Paragraph, Row, Table};
+use crate::currency::DEFAULT_CURRENCY;
+use crate::error::Result;
+use crate::locale::Locale;
+use crate::model::{first_of_month, RecordFilter};
+use crate::money::Money;
+use crate::popup::popup_area;
+use crate::repository::Repository;
+
+const MONTH_NAMES: [&str; 12] = [
+    "Ιανουάριος", "Φεβρουάριος", "Μάρτιος", "Απρίλιος", "Μάιος", "Ιούνιος",
+    "Ιούλιος", "Αύγουστος", "Σεπτέμβριος", "Οκτώβριος", "Νοέμβριος", "Δεκέμβριος",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["Δευ", "Τρί", "Τετ", "Πέμ", "Παρ", "Σάβ", "Κυρ"];
+
+/// What the app should do after a key was handled by the calendar.
+pub enum CalendarAction {
+    None,
+    /// Write this date into the date field.
+    Pick(NaiveDate),
+    /// Type the date instead, e.g. `yesterday` or `14/3`.
+    Type,
+    Close,
+}
+
+/// Month grid opened with `d`, marking the days that have records with what they add up to.
+pub struct CalendarState {
+    pub selected: NaiveDate,
+    /// Spending per day of the selected month in the base currency, `None` when a record
+    /// of that day has no exchange rate.
+    pub totals: IndexMap<NaiveDate, Option<Money>>,
+    pub base_currency: String,
+}
+
+impl CalendarState {
+    pub fn new() -> Self {
+        Self { selected: Utc::now().date_naive(), totals: IndexMap::new(), base_currency: DEFAULT_CURRENCY.to_string() }
+    }
+
+    /// Shows the month of `date` with `date` selected.
+    pub fn open(&mut self, date: NaiveDate, repo: &dyn Repository) -> Result<()> {
+        self.selected = date;
+        self.load_month(repo)
+    }
+
+    fn load_month(&mut self, repo: &dyn Repository) -> Result<()> {
+        let first = first_of_month(self.selected);
+        let last = first + Months::new(1) - Days::new(1);
+        let holder = repo.records_between(first, last, &RecordFilter::default())?;
+        self.totals.clear();
+        for record in &holder.records {
+            let total = self.totals.entry(record.date).or_insert(Some(Money::ZERO));
+            *total = total.zip(record.base_total()).map(|(total, amount)| total + amount);
+        }
+        self.base_currency = holder.base_currency;
+        Ok(())
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<CalendarAction> {
+        let month = first_of_month(self.selected);
+        let moved = match key.code {
+            KeyCode::Esc => return Ok(CalendarAction::Close),
+            KeyCode::Enter => return Ok(CalendarAction::Pick(self.selected)),
+            KeyCode::Tab => return Ok(CalendarAction::Type),
+            KeyCode::Left => self.selected.checked_sub_days(Days::new(1)),
+            KeyCode::Right => self.selected.checked_add_days(Days::new(1)),
+            KeyCode::Up => self.selected.checked_sub_days(Days::new(7)),
+            KeyCode::Down => self.selected.checked_add_days(Days::new(7)),
+            KeyCode::PageUp => self.selected.checked_sub_months(Months::new(1)),
+            KeyCode::PageDown => self.selected.checked_add_months(Months::new(1)),
+            _ => None,
+        };
+        if let Some(date) = moved {
+            self.selected = date;
+            if first_of_month(date) != month {
+                self.load_month(repo)?;
+            }
+        }
+        Ok(CalendarAction::None)
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, locale: Locale) {
+        let first = first_of_month(self.selected);
+        let today = Utc::now().date_naive();
+        // Monday of the week the month starts in
+        let mut day = first - Days::new(first.weekday().num_days_from_monday() as u64);
+        let mut rows = vec![];
+        while day.month() == first.month() || day < first {
+            let cells: Vec<Cell> = (0..7).map(|_| {
+                let cell = self.day_cell(day, first, today, locale);
+                day = day + Days::new(1);
+                cell
+            }).collect();
+            rows.push(Row::new(cells).height(2));
+        }
+
+        // two lines per week, the header with its margin and the borders
+        let height = rows.len() as u16 * 2 + 4;
+        let [grid_area, help_area] = Layout::vertical([Constraint::Length(height), Constraint::Length(1)])
+            .flex(Flex::Center)
+            .areas(popup_area(area, 70, 100));
+        frame.render_widget(Clear, grid_area);
+
+        let header = Row::new(WEEKDAY_NAMES.map(|name| Cell::from(name).bold())).bottom_margin(1);
+        let month_total: Money = self.totals.values().flatten().sum();
+        let unknown = if self.totals.values().any(Option::is_none) { " + ?" } else { "" };
+        let title = format!(
+            "{} {} · {}{}",
+            MONTH_NAMES[first.month0() as usize],
+            first.year(),
+            locale.money(month_total, &self.base_currency),
+            unknown
+        );
+        let table = Table::new(rows, [Constraint::Ratio(1, 7); 7])
+            .header(header)
+            .block(Block::bordered().title(title).border_style(Color::Yellow))
+            .style(Color::Green);
+        frame.render_widget(table, grid_area);
+
+        let help = Line::from(vec![
+            "←→↑↓".green().bold(), " ημέρα/εβδομάδα ".green(),
+            "PgUp/PgDn".green().bold(), " μήνας ".green(),
+            "Enter".green().bold(), " επιλογή ".green(),
+            "Tab".green().bold(), " πληκτρολόγηση ".green(),
+            "Esc".green().bold(), " κλείσιμο".green(),
+        ]);
+        frame.render_widget(Paragraph::new(help).on_black(), help_area);
+    }
+
+    /// Day number with its total underneath; days of the neighbouring months stay blank.
+    fn day_cell(&self, day: NaiveDate, first: NaiveDate, today: NaiveDate, locale: Locale) -> Cell<'static> {
+        if day.month() != first.month() {
+            return Cell::from("");
+        }
+        let mut style = Style::new();
+        let mut lines = vec![Line::from(day.day().to_string())];
+        if let Some(total) = self.totals.get(&day) {
+            style = style.fg(Color::Yellow);
+            lines.push(Line::from(total.map_or("?".to_string(), |total| locale.money(total, &self.base_currency))));
+        }
+        if day == today {
+            style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+        if day == self.selected {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        Cell::from(Text::from(lines)).style(style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use crate::model::Record;
+    use crate::repository::memory::InMemoryRepository;
+
+    fn d(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn press(calendar: &mut CalendarState, code: KeyCode, repo: &dyn Repository) -> CalendarAction {
+        calendar.on_key(KeyEvent::new(code, KeyModifiers::NONE), repo).unwrap()
+    }
+
+    #[test]
+    fn test_move_and_pick() {
+        let repo = InMemoryRepository::new();
+        for (date, amount) in [("2025-03-02", "10"), ("2025-03-02", "2.5"), ("2025-04-30", "7")] {
+            let amounts = [(1, amount.parse().unwrap())].into_iter().collect();
+            repo.save_record(&Record { id: 0, amounts, comments: String::new(), date: d(date), currency: "EUR".to_string(), rate: None }).unwrap();
+        }
+        let mut calendar = CalendarState::new();
+        calendar.open(d("2025-03-31"), &repo).unwrap();
+        assert_eq!(calendar.totals.get(&d("2025-03-02")), Some(&Some("12.5".parse().unwrap())));
+
+        // a day with a record that has no exchange rate has no total, not a zero one
+        let amounts = [(1, "20".parse().unwrap())].into_iter().collect();
+        repo.save_record(&Record { id: 0, amounts, comments: String::new(), date: d("2025-03-02"), currency: "USD".to_string(), rate: None }).unwrap();
+        calendar.open(d("2025-03-31"), &repo).unwrap();
+        assert_eq!(calendar.totals.get(&d("2025-03-02")), Some(&None));
+
+        press(&mut calendar, KeyCode::Right, &repo);
+        assert_eq!(calendar.selected, d("2025-04-01"));
+        assert_eq!(calendar.totals.keys().collect::<Vec<_>>(), [&d("2025-04-30")], "the next month's totals are loaded");
+        press(&mut calendar, KeyCode::Up, &repo);
+        assert_eq!(calendar.selected, d("2025-03-25"));
+        press(&mut calendar, KeyCode::PageUp, &repo);
+        assert_eq!(calendar.selected, d("2025-02-25"));
+        press(&mut calendar, KeyCode::PageDown, &repo);
+        press(&mut calendar, KeyCode::Down, &repo);
+        assert!(matches!(press(&mut calendar, KeyCode::Enter, &repo), CalendarAction::Pick(date) if date == d("2025-04-01")));
+        assert!(matches!(press(&mut calendar, KeyCode::Tab, &repo), CalendarAction::Type));
+        assert!(matches!(press(&mut calendar, KeyCode::Esc, &repo), CalendarAction::Close));
+    }
+}
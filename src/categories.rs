@@ -0,0 +1,246 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tui_textarea::TextArea;
+use chrono::{NaiveDate, Utc};
+use indexmap::IndexMap;
+use crate::input_validator::convert_to_money;
+use crate::locale::Locale;
+use crate::model::{first_of_month, Category};
+use crate::money::Money;
+use crate::repository::Repository;
+use crate::error::{BuldakError, Result};
+use crate::popup::popup_area;
+
+/// Colours offered when cycling with `c`, stored by name in the categories table.
+const COLOURS: &[&str] = &["green", "yellow", "cyan", "magenta", "blue", "red", "lightgreen", "lightblue", "white"];
+
+pub enum CategoriesMode {
+    Browse,
+    Adding,
+    Renaming,
+    Budget,
+}
+
+/// What the app should do after a key was handled by the categories screen.
+pub enum CategoriesAction {
+    None,
+    Changed,
+    Close,
+}
+
+pub struct CategoriesState<'a> {
+    pub categories: Vec<Category>,
+    pub list_state: ListState,
+    pub mode: CategoriesMode,
+    pub name_input: TextArea<'a>,
+    /// Month the budgets are shown for, and set from with `b`.
+    pub budget_month: NaiveDate,
+    pub budgets: IndexMap<i64, Money>,
+    /// Separators budgets are typed with.
+    pub locale: Locale,
+}
+
+impl CategoriesState<'_> {
+    pub fn new() -> Self {
+        Self {
+            categories: vec![],
+            list_state: ListState::default(),
+            mode: CategoriesMode::Browse,
+            name_input: TextArea::default(),
+            budget_month: first_of_month(Utc::now().date_naive()),
+            budgets: IndexMap::new(),
+            locale: Locale::Plain,
+        }
+    }
+
+    pub fn reload(&mut self, repo: &dyn Repository) -> Result<()> {
+        self.categories = repo.categories()?;
+        self.budgets = repo.budgets_for_month(self.budget_month)?;
+        self.locale = Locale::load(repo)?;
+        if self.list_state.selected().is_none_or(|i| i >= self.categories.len()) {
+            self.list_state.select(if self.categories.is_empty() { None } else { Some(0) });
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&Category> {
+        self.list_state.selected().and_then(|i| self.categories.get(i))
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<CategoriesAction> {
+        match self.mode {
+            CategoriesMode::Browse => self.on_browse_key(key, repo),
+            CategoriesMode::Adding | CategoriesMode::Renaming | CategoriesMode::Budget => self.on_name_key(key, repo),
+        }
+    }
+
+    fn on_browse_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<CategoriesAction> {
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc | KeyCode::Char('q')) => return Ok(CategoriesAction::Close),
+            (KeyModifiers::SHIFT, KeyCode::Up) | (_, KeyCode::Char('K')) => return self.move_selected(-1, repo),
+            (KeyModifiers::SHIFT, KeyCode::Down) | (_, KeyCode::Char('J')) => return self.move_selected(1, repo),
+            (_, KeyCode::Down | KeyCode::Char('j')) => self.list_state.select_next(),
+            (_, KeyCode::Up | KeyCode::Char('k')) => self.list_state.select_previous(),
+            (_, KeyCode::Char('a')) => {
+                self.name_input = TextArea::default();
+                self.mode = CategoriesMode::Adding;
+            }
+            (_, KeyCode::Char('r')) => {
+                if let Some(category) = self.selected() {
+                    self.name_input = TextArea::new(vec![category.name.clone()]);
+                    self.name_input.move_cursor(tui_textarea::CursorMove::End);
+                    self.mode = CategoriesMode::Renaming;
+                }
+            }
+            (_, KeyCode::Char('b')) => {
+                if let Some(category) = self.selected() {
+                    let current = self.budgets.get(&category.id).map(|budget| self.locale.number(*budget)).unwrap_or_default();
+                    self.name_input = TextArea::new(vec![current]);
+                    self.name_input.move_cursor(tui_textarea::CursorMove::End);
+                    self.mode = CategoriesMode::Budget;
+                }
+            }
+            (_, KeyCode::Char('x')) => {
+                if let Some(category) = self.selected() {
+                    repo.set_category_archived(category.id, !category.archived)?;
+                    return self.changed(repo);
+                }
+            }
+            (_, KeyCode::Char('c')) => {
+                if let Some(category) = self.selected() {
+                    let next = COLOURS
+                        .iter()
+                        .position(|c| *c == category.colour)
+                        .map_or(0, |i| (i + 1) % COLOURS.len());
+                    repo.set_category_colour(category.id, COLOURS[next])?;
+                    return self.changed(repo);
+                }
+            }
+            _ => {}
+        }
+        Ok(CategoriesAction::None)
+    }
+
+    fn on_name_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<CategoriesAction> {
+        match key.code {
+            KeyCode::Esc => self.mode = CategoriesMode::Browse,
+            KeyCode::Enter => {
+                let name = self.name_input.lines()[0].trim().to_string();
+                let mode = std::mem::replace(&mut self.mode, CategoriesMode::Browse);
+                if name.is_empty() {
+                    return Ok(CategoriesAction::None);
+                }
+                match mode {
+                    CategoriesMode::Adding => {
+                        let colour = COLOURS[self.categories.len() % COLOURS.len()];
+                        repo.add_category(&name, colour)?;
+                        self.list_state.select(Some(self.categories.len()));
+                    }
+                    CategoriesMode::Renaming => {
+                        if let Some(category) = self.selected() {
+                            repo.rename_category(category.id, &name)?;
+                        }
+                    }
+                    CategoriesMode::Budget => {
+                        if let Some(category) = self.selected() {
+                            let amount = convert_to_money(&name, self.locale)?;
+                            if amount.is_negative() {
+                                return Err(BuldakError::InvalidAmount { input: name, reason: "a budget can't be negative".to_string() });
+                            }
+                            repo.set_budget(category.id, self.budget_month, amount)?;
+                        }
+                    }
+                    CategoriesMode::Browse => {}
+                }
+                return self.changed(repo);
+            }
+            _ => {
+                self.name_input.input(key);
+            }
+        }
+        Ok(CategoriesAction::None)
+    }
+
+    fn move_selected(&mut self, offset: isize, repo: &dyn Repository) -> Result<CategoriesAction> {
+        let Some(index) = self.list_state.selected() else {
+            return Ok(CategoriesAction::None);
+        };
+        let Some(target) = index.checked_add_signed(offset).filter(|t| *t < self.categories.len()) else {
+            return Ok(CategoriesAction::None);
+        };
+        repo.swap_category_order(&self.categories[index], &self.categories[target])?;
+        self.list_state.select(Some(target));
+        self.changed(repo)
+    }
+
+    fn changed(&mut self, repo: &dyn Repository) -> Result<CategoriesAction> {
+        self.reload(repo)?;
+        Ok(CategoriesAction::Changed)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let area = popup_area(area, 50, 60);
+        frame.render_widget(Clear, area);
+        let [list_area, input_area, help_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let items = self.categories.iter().map(|c| {
+            let mut spans = vec![
+                Span::styled("■ ", Style::default().fg(c.color())),
+                Span::raw(c.name.clone()),
+                Span::raw(format!("  ({})", c.key)).dark_gray(),
+            ];
+            if let Some(budget) = self.budgets.get(&c.id) {
+                spans.push(Span::raw(format!("  προϋπολογισμός {}", self.locale.number(*budget))).yellow());
+            }
+            if c.archived {
+                spans.push(Span::raw("  [αρχειοθετημένη]").dark_gray().italic());
+            }
+            ListItem::new(Line::from(spans))
+        });
+        let list = List::new(items)
+            .block(Block::bordered().title("Κατηγορίες").border_style(Color::Green))
+            .style(Color::Green)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("+++> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let title = match self.mode {
+            CategoriesMode::Browse => None,
+            CategoriesMode::Adding => Some("Νέα κατηγορία".to_string()),
+            CategoriesMode::Renaming => Some("Μετονομασία".to_string()),
+            CategoriesMode::Budget => Some(format!("Μηνιαίος προϋπολογισμός από {} (0 = χωρίς)", self.budget_month.format("%b-%Y"))),
+        };
+        if let Some(title) = title {
+            self.name_input.set_cursor_line_style(Style::default());
+            self.name_input.set_block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::Yellow))
+                    .title(title),
+            );
+            frame.render_widget(&self.name_input, input_area);
+        } else {
+            frame.render_widget(Block::bordered().border_style(Color::Green), input_area);
+        }
+
+        let help = Line::from(vec![
+            "a".green().bold(), " νέα ".green(),
+            "r".green().bold(), " μετονομασία ".green(),
+            "K/J".green().bold(), " μετακίνηση ".green(),
+            "c".green().bold(), " χρώμα ".green(),
+            "b".green().bold(), " προϋπολογισμός ".green(),
+            "x".green().bold(), " αρχειοθέτηση ".green(),
+            "Esc".green().bold(), " κλείσιμο".green(),
+        ]);
+        frame.render_widget(Paragraph::new(help).on_black(), help_area);
+    }
+}
@@ -1,17 +1,44 @@
 use chrono::{Duration, NaiveDate};
 use indexmap::IndexMap;
 use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style, Stylize},
     text::Line,
     widgets::{Bar, BarChart, BarGroup, Block},
 };
-use crate::db_repo::{get_records_holder, Record};
+use crate::locale::Locale;
+use crate::model::{first_of_month, Record, RecordsHolder};
+use crate::period::Period;
+use crate::budget::BudgetLevel;
+use crate::money::Money;
 
-pub fn vertical_barchart(current_month : NaiveDate) -> BarChart<'static> {
-    let records_holder = get_records_holder(current_month.clone()).unwrap();
-    let bars: Vec<Bar> = create_time_serie(records_holder.records, current_month)
+pub fn render_charts(frame: &mut Frame, area: Rect, records_holder: &RecordsHolder, period: &Period, locale: Locale) {
+    let [days_area, categories_area] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(40),
+    ]).areas(area);
+    frame.render_widget(vertical_barchart(records_holder, period, locale), days_area);
+    frame.render_widget(categories_barchart(records_holder, locale), categories_area);
+}
+
+fn vertical_barchart(records_holder: &RecordsHolder, period: &Period, locale: Locale) -> BarChart<'static> {
+    // a bar turns amber or red once the running total gets near or over the budget
+    let budget = records_holder.total_budget();
+    // only spending in budgeted categories counts against the budget
+    let budgeted: Vec<Record> = records_holder.records.iter().map(|record| {
+        let mut record = record.clone();
+        record.amounts.retain(|category_id, _| records_holder.budgets.contains_key(category_id));
+        record
+    }).collect();
+    let mut running = Money::ZERO;
+    let bars: Vec<Bar> = create_time_serie(&records_holder.records, period)
         .iter()
-        .map(|(date, value)| vertical_bar(date, *value))
+        .zip(create_time_serie(&budgeted, period).values())
+        .map(|((date, value), budgeted)| {
+            running += *budgeted;
+            vertical_bar(date, locale.rounded(*value, &records_holder.base_currency), *value, BudgetLevel::of(running, budget))
+        })
         .collect();
     let title = Line::from("Charts (Sums rounded)").style(Color::Green).centered();
     BarChart::default()
@@ -20,38 +47,83 @@ pub fn vertical_barchart(current_month : NaiveDate) -> BarChart<'static> {
         .bar_width(8)
 }
 
-fn vertical_bar(date: &str, expenses: f32) -> Bar<'static> {
+/// Totals per category, each bar in its category colour unless near or over its budget.
+fn categories_barchart(records_holder: &RecordsHolder, locale: Locale) -> BarChart<'static> {
+    let bars: Vec<Bar> = records_holder.categories
+        .iter()
+        .map(|c| {
+            let total = records_holder.total(c.id);
+            let style = match BudgetLevel::of(total, records_holder.budget(c.id)) {
+                BudgetLevel::Fine => Style::new().fg(c.color()),
+                level => temperature_style(level),
+            };
+            Bar::default()
+                .value(bar_height(total))
+                .label(Line::from(c.name.clone()))
+                .text_value(locale.rounded(total, &records_holder.base_currency))
+                .style(style)
+                .value_style(style.reversed())
+        })
+        .collect();
+    let title = Line::from("Ανά κατηγορία").style(Color::Green).centered();
+    BarChart::default()
+        .direction(Direction::Horizontal)
+        .data(BarGroup::default().bars(&bars))
+        .block(Block::new().title(title))
+        .bar_width(1)
+        .bar_gap(1)
+}
+
+fn vertical_bar(date: &str, text: String, expenses: Money, level: BudgetLevel) -> Bar<'static> {
     Bar::default()
-        .value(expenses.round() as u64)
-        .label(Line::from(format!("{}", date)).style(Color::Green))
-        .text_value(format!("{:.0}",expenses))
-        .style(temperature_style(expenses))
-        .value_style(temperature_style(expenses).reversed())
+        .value(bar_height(expenses))
+        .label(Line::from(date.to_string()).style(Color::Green))
+        .text_value(text)
+        .style(temperature_style(level))
+        .value_style(temperature_style(level).reversed())
+}
+
+fn bar_height(value: Money) -> u64 {
+    value.to_f64().round().max(0.0) as u64
 }
 
-fn temperature_style(_value: f32) -> Style {
-    Style::new().fg(Color::Green)
+const AMBER: Color = Color::Rgb(255, 191, 0);
+
+/// Green, amber or red depending on how close a total is to its budget.
+pub fn temperature_style(level: BudgetLevel) -> Style {
+    match level {
+        BudgetLevel::Fine => Style::new().fg(Color::Green),
+        BudgetLevel::Near => Style::new().fg(AMBER),
+        BudgetLevel::Over => Style::new().fg(Color::Red),
+    }
 }
 
-fn create_time_serie(records : Vec<Record>, mut current_month: NaiveDate) -> IndexMap<String,f32>{
-    let expenses = flatten_by_dates(&records);
-    let mut serie :IndexMap<NaiveDate, f32> = IndexMap::new();
-    for _i in 1..=32 {
-        serie .insert(current_month, 0.0);
-        current_month = current_month + Duration::days(1);
+/// One bar per day for a month or a short range, one per month for anything longer.
+fn create_time_serie(records : &[Record], period: &Period) -> IndexMap<String,Money>{
+    let (from, to) = period.bounds();
+    let daily = period.days() <= 31;
+    let bucket = |date: NaiveDate| if daily { date } else { first_of_month(date) };
+    let mut serie :IndexMap<NaiveDate, Money> = IndexMap::new();
+    let mut day = from;
+    while day <= to {
+        serie.insert(bucket(day), Money::ZERO);
+        day += Duration::days(1);
+    }
+    for (date, value) in flatten_by_dates(records) {
+        *serie.entry(bucket(date)).or_default() += value;
     }
-    serie.extend(expenses);
     serie.sort_keys();
-    let result :IndexMap<String,f32> =serie.into_iter().map(|(date, value)| {
-        (date.format("%b-%d").to_string(), value)
+    let format = if daily { "%b-%d" } else { "%b-%y" };
+    let result :IndexMap<String,Money> =serie.into_iter().map(|(date, value)| {
+        (date.format(format).to_string(), value)
     }).collect();
     result
 }
 
-fn flatten_by_dates(records: &[Record]) -> IndexMap<NaiveDate, f32> {
+fn flatten_by_dates(records: &[Record]) -> IndexMap<NaiveDate, Money> {
     let mut map = IndexMap::new();
     for record in records {
-        *map.entry(record.date).or_default() += record.get_day_summary();
+        *map.entry(record.date).or_default() += record.base_total().unwrap_or_default();
     }
     map
-}
\ No newline at end of file
+}
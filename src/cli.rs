@@ -0,0 +1,432 @@
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+use chrono::{NaiveDate, Utc};
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use crate::backup::{find_backup, list_backups, restore_backup};
+use crate::currency::{normalize_currency, parse_rates_csv, ExchangeRate};
+use crate::db_repo::SqliteRepository;
+use crate::error::{self, BuldakError};
+use crate::export::{single_char, write_csv, CsvOptions};
+use crate::import::{commit_import, plan_import, preview_lines, ImportOptions};
+use crate::input_validator::{into_record, resolve_date};
+use crate::journal::{first_difference, write_journal, JournalFormat, JournalOptions};
+use crate::locale::Locale;
+use crate::model::{first_of_month, Record, RecordsHolder};
+use crate::money::Money;
+use crate::period::Period;
+use crate::repository::Repository;
+
+/// Exit status of `add`, `list` and `total` when the command line itself is wrong.
+pub const EXIT_USAGE: u8 = 2;
+/// Exit status when an amount, date, category or currency was rejected; nothing was written.
+pub const EXIT_INVALID_INPUT: u8 = 3;
+/// Exit status when the database or an output file could not be opened, read or written.
+pub const EXIT_DATABASE: u8 = 4;
+/// Exit status of `export --check` when the file no longer matches the records, like `diff`.
+pub const EXIT_CHANGED: u8 = 1;
+
+/// `buldak restore` lists backups, `buldak restore <n|path>` restores one.
+pub fn restore(db_path: &Path, args: &[String]) -> Result<()> {
+    match args {
+        [] => {
+            let backups = list_backups(db_path)?;
+            if backups.is_empty() {
+                println!("No backups found for {}", db_path.display());
+            }
+            for (number, backup) in backups.iter().enumerate() {
+                println!("{:>3}  {}  {}", number + 1, backup.taken_at.format("%Y-%m-%d %H:%M:%S"), backup.path.display());
+            }
+            Ok(())
+        }
+        [choice] => {
+            let backup = find_backup(db_path, choice)?;
+            restore_backup(db_path, &backup)?;
+            println!("Restored {} from {}", db_path.display(), backup.display());
+            Ok(())
+        }
+        _ => Err(eyre!("usage: buldak restore [<number>|<path>]")),
+    }
+}
+
+/// `buldak rates` lists the exchange rates, the other forms add to them or change the base currency.
+pub fn rates(db_path: &Path, args: &[String]) -> Result<()> {
+    let repo = SqliteRepository::open(db_path)?;
+    match args {
+        [] => {
+            println!("Base currency: {}", repo.base_currency()?);
+            for rate in repo.exchange_rates()? {
+                println!("{}  1 {} = {} {}", rate.date.format("%Y-%m-%d"), rate.from, rate.rate, rate.to);
+            }
+        }
+        [command, currency] if command == "base" => {
+            let currency = normalize_currency(currency)?;
+            repo.set_base_currency(&currency)?;
+            println!("Base currency set to {}", currency);
+        }
+        [command, date, from, to, rate] if command == "add" => {
+            let date = date.parse().map_err(|_| BuldakError::InvalidDate(date.to_string()))?;
+            let rate = rate.parse().map_err(|_| BuldakError::InvalidRate(rate.to_string()))?;
+            repo.add_exchange_rates(&[ExchangeRate::new(date, from, to, rate)?])?;
+            println!("Added rate for {}", date);
+        }
+        [command, file] if command == "import" => {
+            let rates = parse_rates_csv(&fs::read_to_string(file)?)?;
+            let count = repo.add_exchange_rates(&rates)?;
+            println!("Imported {} rates from {}", count, file);
+        }
+        _ => return Err(eyre!(
+            "usage: buldak rates [base <CUR> | add <date> <from> <to> <rate> | import <file.csv>]"
+        )),
+    }
+    Ok(())
+}
+
+/// `buldak locale` shows how amounts are typed and shown in the app, `buldak locale el`
+/// changes it to `1.234,56 €`, `en` to `€1,234.56` and `plain` back to `1234.56`.
+pub fn locale(db_path: &Path, args: &[String]) -> Result<()> {
+    let repo = SqliteRepository::open(db_path)?;
+    match args {
+        [] => {
+            let locale = Locale::load(&repo)?;
+            println!("Locale: {} (e.g. {})", locale, locale.money(Money::from_cents(123456), &repo.base_currency()?));
+        }
+        [name] => {
+            let locale: Locale = name.parse()?;
+            locale.save(&repo)?;
+            println!("Locale set to {}", locale);
+        }
+        _ => return Err(eyre!("usage: buldak locale [plain | el | en]")),
+    }
+    Ok(())
+}
+
+/// `buldak tags [<from>..<to>]` prints what each tag adds up to, over every record by default.
+pub fn tags(db_path: &Path, args: &[String]) -> Result<()> {
+    let repo = SqliteRepository::open(db_path)?;
+    let (from, to) = match args {
+        [] => {
+            let months = repo.months()?;
+            match (months.last(), months.first()) {
+                (Some(first), Some(last)) => (*first, Period::Month(*last).bounds().1),
+                _ => return Ok(()),
+            }
+        }
+        [range] => Period::parse_range(range)?.bounds(),
+        _ => return Err(eyre!("usage: buldak tags [<from>..<to>]")),
+    };
+    let currency = repo.base_currency()?;
+    for (tag, total) in repo.tag_totals(from, to)? {
+        println!("#{:<20} {:>12} {}", tag, total.to_string(), currency);
+    }
+    Ok(())
+}
+
+/// Prints the error of a scripting command to stderr and picks the exit status for it.
+pub fn exit_code(result: error::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("buldak: {}", err);
+            ExitCode::from(exit_status(&err))
+        }
+    }
+}
+
+fn exit_status(err: &BuldakError) -> u8 {
+    match err {
+        BuldakError::Config(_) => EXIT_USAGE,
+        BuldakError::InvalidAmount { .. }
+        | BuldakError::InvalidDate(_)
+        | BuldakError::UnknownCategory(_)
+        | BuldakError::InvalidSchedule(_)
+        | BuldakError::InvalidCurrency(_)
+        | BuldakError::InvalidRate(_) => EXIT_INVALID_INPUT,
+        BuldakError::Db(_) | BuldakError::Migration(_) | BuldakError::Backup(_) | BuldakError::Io(_) => EXIT_DATABASE,
+        BuldakError::ExportChanged { .. } => EXIT_CHANGED,
+    }
+}
+
+/// `--name value` and `--name=value` pairs, in the order given.
+fn parse_flags(args: &[String], usage: &str) -> error::Result<Vec<(String, String)>> {
+    let usage = || BuldakError::Config(format!("usage: {}", usage));
+    let mut flags = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let name = arg.strip_prefix("--").ok_or_else(usage)?;
+        let (name, value) = match name.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => (name, args.next().ok_or_else(usage)?.clone()),
+        };
+        flags.push((name.to_string(), value));
+    }
+    Ok(flags)
+}
+
+/// `--month 2025-03`, the current month without it.
+fn month_flag(args: &[String], usage: &str) -> error::Result<Period> {
+    let mut month = first_of_month(Utc::now().date_naive());
+    for (name, value) in parse_flags(args, usage)? {
+        match name.as_str() {
+            "month" => {
+                month = NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+                    .map_err(|_| BuldakError::InvalidDate(value))?;
+            }
+            _ => return Err(BuldakError::Config(format!("usage: {}", usage))),
+        }
+    }
+    Ok(Period::Month(month))
+}
+
+const ADD_USAGE: &str = "buldak add --<category key> <amount>... [--date YYYY-MM-DD | yesterday | -3 | 14/3] [--comment <text>] [--currency <CUR>]";
+
+/// `buldak add --store 12+3 --beer 4 --date 2025-03-02 --comment lidl` saves one record.
+pub fn add(db_path: &Path, args: &[String]) -> error::Result<()> {
+    let repo = SqliteRepository::open(db_path)?;
+    let record = add_record(&repo, args, Utc::now().date_naive())?;
+    println!("{}\t{}\t{} {}", record.id, record.date.format("%Y-%m-%d"), record.get_day_summary(), record.currency);
+    Ok(())
+}
+
+/// Reads the flags the way the input fields would be read, so `--store 54.20 --beer -12`
+/// splits the receipt the same way as in the app.
+fn add_record(repo: &dyn Repository, args: &[String], today: NaiveDate) -> error::Result<Record> {
+    let categories: Vec<_> = repo.categories()?.into_iter().filter(|c| !c.archived).collect();
+    let mut amounts: Vec<(i64, String)> = categories.iter().map(|c| (c.id, String::new())).collect();
+    let mut date = today.format("%Y-%m-%d").to_string();
+    let mut comment = String::new();
+    let mut currency = repo.base_currency()?;
+    for (name, value) in parse_flags(args, ADD_USAGE)? {
+        match name.as_str() {
+            "date" => date = resolve_date(&value, today)?.format("%Y-%m-%d").to_string(),
+            "comment" => comment = value,
+            "currency" => currency = value,
+            key => {
+                let index = categories.iter().position(|c| c.key == key)
+                    .ok_or_else(|| BuldakError::UnknownCategory(key.to_string()))?;
+                amounts[index].1 = value;
+            }
+        }
+    }
+    let amounts: Vec<(i64, &str)> = amounts.iter().map(|(id, amount)| (*id, amount.as_str())).collect();
+    let mut record = into_record(&amounts, &comment, &date, &currency, Locale::load(repo)?)?;
+    if record.amounts.values().all(|amount| amount.is_zero()) {
+        return Err(BuldakError::InvalidAmount { input: args.join(" "), reason: "no amount".to_string() });
+    }
+    record.id = repo.save_record(&record)?;
+    Ok(record)
+}
+
+/// `buldak list [--month 2025-03]` prints one tab separated line per record.
+pub fn list(db_path: &Path, args: &[String]) -> error::Result<()> {
+    let period = month_flag(args, "buldak list [--month YYYY-MM]")?;
+    let repo = SqliteRepository::open(db_path)?;
+    for line in list_lines(&repo.records_for_period(&period)?) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn list_lines(holder: &RecordsHolder) -> Vec<String> {
+    holder.records.iter().map(|record| {
+        let amounts = holder.categories.iter()
+            .filter_map(|c| record.amounts.get(&c.id).map(|amount| format!("{}={}", c.key, amount)))
+            .collect::<Vec<String>>()
+            .join(" ");
+        format!("{}\t{}\t{}\t{} {}\t{}", record.id, record.date.format("%Y-%m-%d"), amounts, record.get_day_summary(), record.currency, record.comments)
+    }).collect()
+}
+
+/// `buldak total [--month 2025-03]` prints the total of each category and of the month,
+/// in the base currency.
+pub fn total(db_path: &Path, args: &[String]) -> error::Result<()> {
+    let period = month_flag(args, "buldak total [--month YYYY-MM]")?;
+    let repo = SqliteRepository::open(db_path)?;
+    let holder = repo.records_for_period(&period)?;
+    for (key, amount) in total_lines(&holder) {
+        println!("{}\t{} {}", key, amount, holder.base_currency);
+    }
+    if !holder.missing_rates.is_empty() {
+        eprintln!("buldak: left out, no exchange rate: {}", holder.missing_rates.join(", "));
+    }
+    Ok(())
+}
+
+fn total_lines(holder: &RecordsHolder) -> Vec<(String, Money)> {
+    let mut lines: Vec<(String, Money)> = holder.categories.iter()
+        .filter(|c| holder.totals.contains_key(&c.id))
+        .map(|c| (c.key.clone(), holder.total(c.id)))
+        .collect();
+    lines.push(("total".to_string(), holder.all_total));
+    lines
+}
+
+const EXPORT_USAGE: &str = "buldak export [--format csv|ledger|hledger|beancount] [--from YYYY-MM-DD --to YYYY-MM-DD] \
+    [--output <file>] [--check <file>] [--delimiter <c>] [--decimal <c>] [--lang el|en] \
+    [--account <category key>=<account>]... [--funding <account>] [--no-open] [--save]";
+
+/// `buldak export --format csv --from 2025-01-01 --to 2025-03-31` writes the records of the
+/// range, the current month without one, to stdout or `--output`. `--save` keeps the CSV
+/// options or the journal accounts given as the defaults, the CSV ones also used by the
+/// export key in the app. `--check <file>` exports again and fails if the file differs.
+/// `--no-open` leaves out the beancount `open` directives, for a month appended to a
+/// ledger whose accounts were opened by an earlier export.
+pub fn export(db_path: &Path, args: &[String]) -> error::Result<()> {
+    let save = args.iter().any(|arg| arg == "--save");
+    let no_open = args.iter().any(|arg| arg == "--no-open");
+    let args: Vec<String> = args.iter().filter(|arg| !["--save", "--no-open"].contains(&arg.as_str())).cloned().collect();
+    let repo = SqliteRepository::open(db_path)?;
+    let mut options = CsvOptions::load(&repo)?;
+    let mut journal_options = JournalOptions { open_accounts: !no_open, ..JournalOptions::load(&repo)? };
+    let (mut format, mut from, mut to, mut output, mut check) = (None, None, None, None, None);
+    for (name, value) in parse_flags(&args, EXPORT_USAGE)? {
+        match name.as_str() {
+            "format" if value == "csv" => format = None,
+            "format" => format = Some(value.parse::<JournalFormat>()?),
+            "from" => from = Some(value),
+            "to" => to = Some(value),
+            "output" => output = Some(value),
+            "check" => check = Some(value),
+            "account" | "funding" => journal_options.set(&name, &value)?,
+            _ => options.set(&name, &value)?,
+        }
+    }
+    let period = match (from, to) {
+        (Some(from), Some(to)) => Period::parse_range(&format!("{}..{}", from, to))?,
+        (None, None) => Period::Month(first_of_month(Utc::now().date_naive())),
+        _ => return Err(BuldakError::Config(format!("usage: {}", EXPORT_USAGE))),
+    };
+    journal_options.check_keys(&repo.categories()?)?;
+    if save {
+        match format {
+            None => options.save(&repo)?,
+            Some(_) => journal_options.save(&repo)?,
+        }
+    }
+    let holder = repo.records_for_period(&period)?;
+    let write = |out: &mut dyn std::io::Write| match format {
+        None => write_csv(&holder, &options, out),
+        Some(format) => write_journal(&holder, &period, format, &journal_options, out),
+    };
+    if let Some(path) = check {
+        let mut fresh = Vec::new();
+        write(&mut fresh)?;
+        let line = first_difference(&fs::read_to_string(&path)?, &String::from_utf8_lossy(&fresh));
+        return match line {
+            Some(line) => Err(BuldakError::ExportChanged { path, line }),
+            None => Ok(()),
+        };
+    }
+    match output {
+        Some(path) => {
+            write(&mut fs::File::create(&path)?)?;
+            eprintln!("Exported {} records to {}", holder.records.len(), path);
+        }
+        None => write(&mut std::io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+const IMPORT_USAGE: &str = "buldak import <file.csv> [--map <date|comment|category key>=<column>]... \
+    [--delimiter <c>] [--decimal <c>] [--date-format %d/%m/%Y] [--currency <CUR>] [--no-header] \
+    [--keep-duplicates] [--dry-run]";
+
+/// `buldak import old.csv --map store=Super --dry-run` shows what would be imported;
+/// without `--dry-run` the rows are saved in one go, rows that look already stored left out.
+pub fn import(db_path: &Path, args: &[String]) -> error::Result<()> {
+    let switch = |name: &str| args.iter().any(|arg| arg == name);
+    let (dry_run, keep_duplicates) = (switch("--dry-run"), switch("--keep-duplicates"));
+    let mut options = ImportOptions { header: !switch("--no-header"), ..Default::default() };
+    let usage = || BuldakError::Config(format!("usage: {}", IMPORT_USAGE));
+    let [file, flags @ ..] = args else { return Err(usage()) };
+    let flags: Vec<String> = flags.iter()
+        .filter(|arg| !["--dry-run", "--keep-duplicates", "--no-header"].contains(&arg.as_str()))
+        .cloned()
+        .collect();
+    for (name, value) in parse_flags(&flags, IMPORT_USAGE)? {
+        match name.as_str() {
+            "map" => {
+                let (target, column) = value.split_once('=').ok_or_else(usage)?;
+                options.mapping.push((target.trim().to_string(), column.trim().to_string()));
+            }
+            "delimiter" => options.delimiter = single_char(&name, &value)?,
+            "decimal" => options.decimal = single_char(&name, &value)?,
+            "date-format" => options.date_format = value,
+            "currency" => options.currency = Some(normalize_currency(&value)?),
+            _ => return Err(usage()),
+        }
+    }
+
+    // a file that isn't there is a wrong argument, not a database failure
+    let text = fs::read_to_string(file).map_err(|err| BuldakError::Config(format!("can't read {}: {}", file, err)))?;
+    let repo = SqliteRepository::open(db_path)?;
+    let rows = plan_import(&repo, &text, &options)?;
+    let lines = preview_lines(&rows, &repo.categories()?);
+    for (row, line) in rows.iter().zip(lines) {
+        if dry_run || row.duplicate || row.record.is_err() {
+            println!("{}", line);
+        }
+    }
+    let duplicates = rows.iter().filter(|row| row.duplicate).count();
+    let invalid = rows.iter().filter(|row| row.record.is_err()).count();
+    if invalid > 0 {
+        eprintln!("buldak: {} of {} rows can't be imported, nothing was saved", invalid, rows.len());
+        // the first bad row decides the exit status
+        return rows.into_iter().find_map(|row| row.record.err()).map_or(Ok(()), Err);
+    }
+    if dry_run {
+        println!("{} rows, {} look already stored; nothing saved (--dry-run)", rows.len(), duplicates);
+        return Ok(());
+    }
+    let saved = commit_import(&repo, &rows, keep_duplicates)?;
+    println!("Imported {} of {} rows from {}", saved, rows.len(), file);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::InMemoryRepository;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn d(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_add_record_from_flags() {
+        let repo = InMemoryRepository::new();
+        let record = add_record(&repo, &args(&["--store", "12+3", "--beer=-4", "--date", "2025-03-02", "--comment", "lidl #food"]), d("2025-03-10")).unwrap();
+        assert_eq!(record.amount(1), "11".parse().unwrap(), "beer taken out of store like in the app");
+        assert_eq!(record.amount(2), "4".parse().unwrap());
+
+        let holder = repo.records_for_period(&Period::Month(d("2025-03-01"))).unwrap();
+        assert_eq!(list_lines(&holder), ["1\t2025-03-02\tstore=11.00 beer=4.00\t15.00 EUR\tlidl #food"]);
+        assert_eq!(total_lines(&holder).last(), Some(&("total".to_string(), "15".parse().unwrap())));
+
+        let today = add_record(&repo, &args(&["--allos", "1", "--currency", "usd"]), d("2025-03-10")).unwrap();
+        assert_eq!((today.date, today.currency.as_str()), (d("2025-03-10"), "USD"));
+        let yesterday = add_record(&repo, &args(&["--allos", "1", "--date", "yesterday"]), d("2025-03-10")).unwrap();
+        assert_eq!(yesterday.date, d("2025-03-09"));
+    }
+
+    #[test]
+    fn test_exit_status_of_bad_input() {
+        let repo = InMemoryRepository::new();
+        let status = |list: &[&str]| exit_status(&add_record(&repo, &args(list), d("2025-03-10")).unwrap_err());
+        assert_eq!(status(&["--wine", "3"]), EXIT_INVALID_INPUT);
+        assert_eq!(status(&["--store", "abc"]), EXIT_INVALID_INPUT);
+        assert_eq!(status(&["--store", "3", "--date", "2025-02-30"]), EXIT_INVALID_INPUT);
+        assert_eq!(status(&["--comment", "nothing"]), EXIT_INVALID_INPUT);
+        assert_eq!(status(&["store", "3"]), EXIT_USAGE);
+        assert_eq!(status(&["--store"]), EXIT_USAGE);
+        assert_eq!(exit_status(&month_flag(&args(&["--month", "March"]), "").unwrap_err()), EXIT_INVALID_INPUT);
+        let missing = std::env::temp_dir().join("buldak-missing-import.csv");
+        assert_eq!(exit_status(&import(Path::new("unused.db"), &args(&[missing.to_str().unwrap()])).unwrap_err()), EXIT_USAGE);
+        assert!(repo.records_for_period(&Period::Month(d("2025-03-01"))).unwrap().records.is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DB_FILENAME: &str = "buldak.sqlite3";
+pub const DB_ENV_VAR: &str = "BULDAK_DB";
+
+/// Options given on the command line.
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    pub db: Option<PathBuf>,
+    pub retention: RetentionPolicy,
+    /// Subcommand such as `restore`; the TUI starts when there is none.
+    pub command: Option<String>,
+    /// Everything after the subcommand that is not a global option.
+    pub command_args: Vec<String>,
+}
+
+/// How many automatic backups survive pruning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Newest backup of each of the last N days that have one.
+    pub keep_daily: usize,
+    /// Newest backup of each of the last M months that have one.
+    pub keep_monthly: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy { keep_daily: 7, keep_monthly: 12 }
+    }
+}
+
+impl Args {
+    pub fn from_env() -> Result<Args, String> {
+        Self::parse(env::args().skip(1))
+    }
+
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| inline_value.clone().or_else(|| args.next()).ok_or(format!("{} expects a value", name));
+            match flag.as_str() {
+                "--db" => parsed.db = Some(PathBuf::from(value("--db")?)),
+                "--keep-daily" => parsed.retention.keep_daily = parse_count("--keep-daily", &value("--keep-daily")?)?,
+                "--keep-monthly" => parsed.retention.keep_monthly = parse_count("--keep-monthly", &value("--keep-monthly")?)?,
+                _ if parsed.command.is_some() => parsed.command_args.push(arg),
+                _ if arg.starts_with('-') => return Err(format!("unknown argument: {}", arg)),
+                _ => parsed.command = Some(arg),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_count(flag: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+}
+
+/// Picks the database file: `--db`, then `BULDAK_DB`, then the XDG data dir
+/// (`$XDG_DATA_HOME/buldak`, falling back to `~/.local/share/buldak`).
+pub fn resolve_db_path(args: &Args) -> Result<PathBuf, String> {
+    resolve_db_path_with(args, |key| env::var_os(key).map(PathBuf::from))
+}
+
+fn resolve_db_path_with<F>(args: &Args, var: F) -> Result<PathBuf, String>
+where
+    F: Fn(&str) -> Option<PathBuf>,
+{
+    if let Some(db) = &args.db {
+        return Ok(db.clone());
+    }
+    if let Some(db) = var(DB_ENV_VAR).filter(|p| !p.as_os_str().is_empty()) {
+        return Ok(db);
+    }
+    let data_home = var("XDG_DATA_HOME")
+        .filter(|p| p.is_absolute())
+        .or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+        .ok_or("cannot locate data directory: neither XDG_DATA_HOME nor HOME is set")?;
+    Ok(data_home.join("buldak").join(DB_FILENAME))
+}
+
+pub fn ensure_parent_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+/// Backups live next to the database: `~/.local/share/buldak/backups`.
+pub fn backup_dir(db_path: &Path) -> PathBuf {
+    match db_path.parent() {
+        Some(dir) => dir.join("backups"),
+        None => PathBuf::from("backups"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Args {
+        Args::parse(list.iter().map(|s| s.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_parse_db_flag() {
+        assert_eq!(args(&["--db", "/tmp/a.sqlite3"]).db, Some(PathBuf::from("/tmp/a.sqlite3")));
+        assert_eq!(args(&["--db=/tmp/b.sqlite3"]).db, Some(PathBuf::from("/tmp/b.sqlite3")));
+        assert_eq!(args(&[]).db, None);
+        assert!(Args::parse(vec!["--db".to_string()]).is_err());
+        assert!(Args::parse(vec!["--nope".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_command_and_retention() {
+        let parsed = args(&["restore", "--keep-daily", "3", "2", "--db=/tmp/a.sqlite3", "--keep-monthly=6"]);
+        assert_eq!(parsed.command.as_deref(), Some("restore"));
+        assert_eq!(parsed.command_args, vec!["2".to_string()]);
+        assert_eq!(parsed.db, Some(PathBuf::from("/tmp/a.sqlite3")));
+        assert_eq!(parsed.retention, RetentionPolicy { keep_daily: 3, keep_monthly: 6 });
+        assert!(Args::parse(vec!["--keep-daily".to_string(), "many".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_db_path_precedence() {
+        let env = |key: &str| match key {
+            DB_ENV_VAR => Some(PathBuf::from("/env/buldak.sqlite3")),
+            "HOME" => Some(PathBuf::from("/home/me")),
+            _ => None,
+        };
+        let from_flag = resolve_db_path_with(&args(&["--db", "/flag/x.sqlite3"]), env).unwrap();
+        assert_eq!(from_flag, PathBuf::from("/flag/x.sqlite3"));
+        let from_env = resolve_db_path_with(&Args::default(), env).unwrap();
+        assert_eq!(from_env, PathBuf::from("/env/buldak.sqlite3"));
+    }
+
+    #[test]
+    fn test_resolve_db_path_xdg() {
+        let xdg = |key: &str| match key {
+            "XDG_DATA_HOME" => Some(PathBuf::from("/data")),
+            "HOME" => Some(PathBuf::from("/home/me")),
+            _ => None,
+        };
+        assert_eq!(
+            resolve_db_path_with(&Args::default(), xdg).unwrap(),
+            PathBuf::from("/data/buldak/buldak.sqlite3")
+        );
+        let home = |key: &str| (key == "HOME").then(|| PathBuf::from("/home/me"));
+        assert_eq!(
+            resolve_db_path_with(&Args::default(), home).unwrap(),
+            PathBuf::from("/home/me/.local/share/buldak/buldak.sqlite3")
+        );
+        assert!(resolve_db_path_with(&Args::default(), |_| None).is_err());
+    }
+
+    #[test]
+    fn test_backup_dir_follows_db() {
+        assert_eq!(
+            backup_dir(Path::new("/data/buldak/buldak.sqlite3")),
+            PathBuf::from("/data/buldak/backups")
+        );
+        assert_eq!(backup_dir(Path::new("money.db")), PathBuf::from("backups"));
+    }
+}
@@ -0,0 +1,147 @@
+use chrono::NaiveDate;
+use crate::error::{BuldakError, Result};
+
+/// Used for records written before currencies existed and as the first base currency.
+pub const DEFAULT_CURRENCY: &str = "EUR";
+
+/// Checks an ISO 4217 style code and upper-cases it, `usd` -> `USD`.
+pub fn normalize_currency(code: &str) -> Result<String> {
+    let code = code.trim();
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(code.to_ascii_uppercase())
+    } else {
+        Err(BuldakError::InvalidCurrency(code.to_string()))
+    }
+}
+
+/// One unit of `from` is worth `rate` units of `to` from `date` on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRate {
+    pub date: NaiveDate,
+    pub from: String,
+    pub to: String,
+    pub rate: f64,
+}
+
+impl ExchangeRate {
+    pub fn new(date: NaiveDate, from: &str, to: &str, rate: f64) -> Result<Self> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(BuldakError::InvalidRate(rate.to_string()));
+        }
+        Ok(ExchangeRate { date, from: normalize_currency(from)?, to: normalize_currency(to)?, rate })
+    }
+}
+
+/// Rates into one base currency, looked up by the day of a record.
+#[derive(Debug, Clone)]
+pub struct Rates {
+    pub base: String,
+    /// `(currency, date, base units per unit)` sorted by currency and date.
+    table: Vec<(String, NaiveDate, f64)>,
+}
+
+impl Rates {
+    /// Keeps the rates that involve `base`, inverting the ones quoted the other way round.
+    pub fn new(base: &str, rates: &[ExchangeRate]) -> Self {
+        let mut table: Vec<(String, NaiveDate, f64)> = rates
+            .iter()
+            .filter_map(|r| {
+                if r.to == base {
+                    Some((r.from.clone(), r.date, r.rate))
+                } else if r.from == base {
+                    Some((r.to.clone(), r.date, 1.0 / r.rate))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        table.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        Rates { base: base.to_string(), table }
+    }
+
+    /// Base units per unit of `currency` on `date`: the latest rate set on or before that day,
+    /// or the earliest one when the record predates every rate.
+    pub fn rate(&self, currency: &str, date: NaiveDate) -> Option<f64> {
+        if currency == self.base {
+            return Some(1.0);
+        }
+        let known = self.table.iter().filter(|(c, _, _)| c == currency);
+        let mut earliest = None;
+        let mut latest_before = None;
+        for (_, day, rate) in known {
+            if earliest.is_none() {
+                earliest = Some(*rate);
+            }
+            if *day <= date {
+                latest_before = Some(*rate);
+            }
+        }
+        latest_before.or(earliest)
+    }
+}
+
+/// Reads `date,from,to,rate` lines, e.g. `2025-03-01,USD,EUR,0.92`. A header line is skipped.
+pub fn parse_rates_csv(text: &str) -> Result<Vec<ExchangeRate>> {
+    let mut rates = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split([',', ';']).map(str::trim).collect();
+        let [date, from, to, rate] = fields[..] else {
+            return Err(BuldakError::Config(format!("line {}: expected date,from,to,rate", number + 1)));
+        };
+        let Ok(date) = date.parse::<NaiveDate>() else {
+            if number == 0 {
+                continue;
+            }
+            return Err(BuldakError::InvalidDate(date.to_string()));
+        };
+        let rate: f64 = rate.parse().map_err(|_| BuldakError::InvalidRate(rate.to_string()))?;
+        rates.push(ExchangeRate::new(date, from, to, rate)?);
+    }
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rate_lookup_by_date_and_direction() {
+        let rates = Rates::new("EUR", &[
+            ExchangeRate::new(d("2025-03-01"), "USD", "EUR", 0.92).unwrap(),
+            ExchangeRate::new(d("2025-04-01"), "USD", "EUR", 0.90).unwrap(),
+            ExchangeRate::new(d("2025-03-01"), "EUR", "GBP", 0.8).unwrap(),
+            ExchangeRate::new(d("2025-03-01"), "USD", "GBP", 0.7).unwrap(),
+        ]);
+        assert_eq!(rates.rate("EUR", d("2020-01-01")), Some(1.0));
+        assert_eq!(rates.rate("USD", d("2025-03-31")), Some(0.92));
+        assert_eq!(rates.rate("USD", d("2025-04-01")), Some(0.90));
+        assert_eq!(rates.rate("USD", d("2024-12-31")), Some(0.92), "earliest known rate");
+        assert_eq!(rates.rate("GBP", d("2025-03-02")), Some(1.25));
+        assert_eq!(rates.rate("JPY", d("2025-03-02")), None);
+    }
+
+    #[test]
+    fn test_parse_rates_csv() {
+        let rates = parse_rates_csv("date,from,to,rate\n2025-03-01, usd ,EUR,0.92\n\n2025-03-02;GBP;EUR;1.19\n").unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!((rates[0].from.as_str(), rates[1].rate), ("USD", 1.19));
+        assert!(parse_rates_csv("2025-03-01,USD,EUR,-1").is_err());
+        assert!(parse_rates_csv("2025-03-01,USD,EUR").is_err());
+        assert!(parse_rates_csv("2025-03-01,USD,EUR,1\nnot-a-date,USD,EUR,1").is_err());
+    }
+
+    #[test]
+    fn test_normalize_currency() {
+        assert_eq!(normalize_currency(" usd").unwrap(), "USD");
+        assert!(normalize_currency("US").is_err());
+        assert!(normalize_currency("€").is_err());
+    }
+}
@@ -1,152 +1,662 @@
-use std::fs;
-use chrono::{Datelike, NaiveDate, Utc};
-use const_format::concatcp;
-use rusqlite::{Connection, Result};
-
-#[cfg(debug_assertions)]
-const SQLITE_FILE_PREFIX:&'static str = "./";
-
-#[cfg(not(debug_assertions))]
-const SQLITE_FILE_PREFIX:&'static str = "/Users/maxim/.buldak/";
-
-const SQL_FILENAME: &'static str = concatcp!(SQLITE_FILE_PREFIX, "buldak.sqlite3");
-const BACKUP_FILENAME: &'static str = concatcp!(SQLITE_FILE_PREFIX, "buldak_backup.sqlite3");
-
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct Record {
-    pub id: i32,
-    pub store: f32,
-    pub beer: f32,
-    pub allos: f32,
-    pub comments: String,
-    pub date: NaiveDate,
+use std::path::Path;
+use chrono::{NaiveDate, Utc};
+use indexmap::IndexMap;
+use rusqlite::{Connection, OptionalExtension, Params};
+use crate::currency::{ExchangeRate, Rates};
+use crate::error::{BuldakError, Result};
+use crate::migrations::{migrate, schema_version, MIGRATIONS};
+use crate::model::{category_key, extract_tags, first_of_month, fold_accents, Category, Record, RecordFilter, RecordsHolder};
+use crate::money::Money;
+use crate::recurring::RecurringRule;
+use crate::repository::{with_current_month, Repository};
+
+/// [`Repository`] backed by the SQLite file, holding one connection for the whole session.
+#[derive(Debug)]
+pub struct SqliteRepository {
+    conn: Connection,
 }
 
-impl Record {
-    pub fn vec_of_fields(&self) -> Vec<String> {
-        vec!(
-             self.date.format("%Y-%m-%d").to_string(),
-             self.store.to_string(),
-             self.beer.to_string(),
-             self.allos.to_string(),
-             format!("{:.2}",self.get_day_summary()),
-             self.comments.to_string(),
-        )
+impl SqliteRepository {
+    /// Opens the database and brings its schema up to date.
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
     }
 
-    pub fn get_day_summary(&self) -> f32 {
-        self.store + self.beer + self.allos
+    pub fn from_connection(mut conn: Connection) -> Result<Self> {
+        // a pending migration that changed what index_record writes has every record indexed again
+        let reindex = MIGRATIONS.iter().skip(schema_version(&conn)? as usize).any(|m| m.reindex);
+        migrate(&mut conn)?;
+        if reindex {
+            Self::index_existing_records(&conn)?;
+        }
+        Ok(SqliteRepository { conn })
     }
 
-}
+    /// Fills the tag tables and the search index from the comments written before they existed.
+    fn index_existing_records(conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        let comments = tx.prepare("SELECT id, IFNULL(comment, '') FROM records")?
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<(i64, String)>>>()?;
+        for (record_id, comment) in comments {
+            Self::index_record(&tx, record_id, &comment)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
 
-#[derive(Debug, Clone)]
-pub struct RecordsHolder {
-    pub records: Vec<Record>,
-    pub store_total : f32,
-    pub beer_total : f32,
-    pub allos_total : f32,
-    pub all_total : f32,
-}
+    /// Replaces the tags and the search entry of a record with what its comment says now.
+    fn index_record(conn: &Connection, record_id: i64, comments: &str) -> Result<()> {
+        let tags = extract_tags(comments);
+        conn.execute("DELETE FROM record_tags WHERE record_id = ?1", [record_id])?;
+        for tag in &tags {
+            conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", [tag])?;
+            conn.execute(
+                "INSERT INTO record_tags (record_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2",
+                (record_id, tag),
+            )?;
+        }
+        conn.execute("DELETE FROM records_fts WHERE rowid = ?1", [record_id])?;
+        conn.execute(
+            "INSERT INTO records_fts (rowid, comment, tags) VALUES (?1, ?2, ?3)",
+            (record_id, fold_accents(comments), fold_accents(&tags.join(" "))),
+        )?;
+        Ok(())
+    }
 
-impl RecordsHolder {
+    fn insert_record(conn: &Connection, record: &Record) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO records (comment,date,currency) VALUES (?1, ?2, ?3)",
+            (&record.comments, &record.date, &record.currency),
+        )?;
+        let record_id = conn.last_insert_rowid();
+        Self::insert_lines(conn, record_id, &record.amounts)?;
+        Self::index_record(conn, record_id, &record.comments)?;
+        Ok(record_id)
+    }
 
-    pub fn new(recs: &Vec<Record>) -> RecordsHolder {
-        let (store_total, beer_total, allos_total, all_total) = Self::calculate_totals(recs);
-        RecordsHolder {
-            records: recs.clone(),
-            store_total,
-            beer_total,
-            allos_total,
-            all_total,
+    fn insert_lines(conn: &Connection, record_id: i64, amounts: &IndexMap<i64, Money>) -> Result<()> {
+        let mut stmt = conn.prepare("INSERT INTO record_lines (record_id,category_id,amount) VALUES (?1, ?2, ?3)")?;
+        for (category_id, amount) in amounts.iter().filter(|(_, amount)| !amount.is_zero()) {
+            stmt.execute((record_id, category_id, amount))?;
         }
+        Ok(())
     }
 
-    fn calculate_totals(recs: &[Record]) -> (f32, f32, f32, f32) {
-        let (store_sum, beer_sum, allos_sum) = recs.iter().fold(
-            (0.0, 0.0, 0.0),
-            |(store, beer, allos), r| {
-                (store + r.store, beer + r.beer, allos + r.allos)
-            },
-        );
-        let total_sum = store_sum + beer_sum + allos_sum;
-        (store_sum, beer_sum, allos_sum, total_sum)
+    /// Records with their lines, `condition` being the rest of the query after `where`.
+    /// Rates are filled in for the current base currency.
+    fn load_records<P: Params>(&self, condition: &str, params: P) -> Result<Vec<Record>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT r.id, r.comment, r.date, r.currency, l.category_id, l.amount FROM records r
+             LEFT JOIN record_lines l ON l.record_id = r.id
+             where {}", condition))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                Record {
+                    id: row.get(0)?,
+                    amounts: IndexMap::new(),
+                    comments: row.get(1)?,
+                    date: row.get(2)?,
+                    currency: row.get(3)?,
+                    rate: None,
+                },
+                row.get::<_, Option<i64>>(4)?,
+                row.get::<_, Option<Money>>(5)?,
+            ))
+        })?;
+        let mut records: Vec<Record> = Vec::new();
+        for row in rows {
+            let (record, category_id, amount) = row?;
+            if records.last().is_none_or(|last| last.id != record.id) {
+                records.push(record);
+            }
+            if let (Some(category_id), Some(amount), Some(last)) = (category_id, amount, records.last_mut()) {
+                last.amounts.insert(category_id, amount);
+            }
+        }
+        let rates = Rates::new(&self.base_currency()?, &self.exchange_rates()?);
+        for record in records.iter_mut() {
+            record.rate = rates.rate(&record.currency, record.date);
+        }
+        Ok(records)
     }
 
+    /// Derives a key from the display name (`Φαρμακείο` -> `φαρμακείο`), suffixed when taken.
+    fn unique_category_key(&self, name: &str) -> Result<String> {
+        let base = category_key(name);
+        let mut key = base.clone();
+        let mut suffix = 2;
+        while self.conn.query_row("SELECT count(*) FROM categories WHERE key = ?1", [&key], |row| row.get::<_, i64>(0))? > 0 {
+            key = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        Ok(key)
+    }
 }
 
-pub fn save_record(record: &Record) -> Result<usize> {
-    let conn = get_connection();
-    conn.execute(
-        "INSERT INTO records (store,beer,allos,comment,date) VALUES (?1, ?2, ?3, ?4, ?5)",
-        (record.store, record.beer, record.allos, &record.comments, &record.date ),
-    )
-}
+impl Repository for SqliteRepository {
+    fn save_record(&self, record: &Record) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        let record_id = Self::insert_record(&tx, record)?;
+        tx.commit()?;
+        Ok(record_id)
+    }
 
-pub fn delete_all() -> Result<usize> {
-    let conn = get_connection();
-    conn.execute(
-        "delete from records",
-        (),
-    )
-}
+    fn save_records(&self, records: &[Record]) -> Result<Vec<i64>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let ids = records.iter().map(|record| Self::insert_record(&tx, record)).collect::<Result<Vec<i64>>>()?;
+        tx.commit()?;
+        Ok(ids)
+    }
 
-pub fn get_records_holder(date : NaiveDate) -> Result<RecordsHolder> {
-    let conn = get_connection();
-    let date_string = date.format("%m-%Y").to_string();
-    let mut stmt = conn.prepare("SELECT id, store, beer, allos, comment, date FROM records where STRFTIME('%m-%Y', date) = ?1 order by date asc")?;
-    let person_iter = stmt.query_map([date_string], |row| {
-        Ok(Record {
-            id: row.get(0)?,
-            store: row.get(1)?,
-            beer: row.get(2)?,
-            allos: row.get(3)?,
-            comments: row.get(4)?,
-            date: row.get(5)?,
-        })
-    })?;
-    let records = person_iter.map(|r| r.unwrap()).collect::<Vec<Record>>();
-    Ok(RecordsHolder::new(&records))
-}
+    fn update_record(&self, record: &Record) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE records SET comment = ?1, date = ?2, currency = ?3 WHERE id = ?4",
+            (&record.comments, &record.date, &record.currency, record.id),
+        )?;
+        tx.execute("DELETE FROM record_lines WHERE record_id = ?1", [record.id])?;
+        Self::insert_lines(&tx, record.id, &record.amounts)?;
+        Self::index_record(&tx, record.id, &record.comments)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_record(&self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE records SET deleted_at = ?1 WHERE id = ?2",
+            (Utc::now().naive_utc(), id),
+        )?;
+        Ok(())
+    }
+
+    fn restore_record(&self, id: i64) -> Result<()> {
+        self.conn.execute("UPDATE records SET deleted_at = NULL WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    fn records_between(&self, from: NaiveDate, to: NaiveDate, filter: &RecordFilter) -> Result<RecordsHolder> {
+        // plain comparisons on the ISO date text so the records_date index is used
+        let mut records = self.load_records(
+            "r.date >= ?1 and r.date <= ?2 and r.deleted_at IS NULL
+               and (?3 IS NULL or r.id IN (SELECT rt.record_id FROM record_tags rt JOIN tags t ON t.id = rt.tag_id WHERE t.name = ?3))
+             order by r.date asc, r.id asc",
+            (from, to, &filter.tag),
+        )?;
+        let base_currency = self.base_currency()?;
+        // SQLite's lower() only folds ASCII, so text and the rest are matched here
+        records.retain(|record| filter.matches(record));
+        Ok(RecordsHolder::new(&records, &self.categories()?, &base_currency))
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<Record>> {
+        let Some(query) = fts_query(query) else { return Ok(vec![]) };
+        let mut records = self.load_records(
+            "r.deleted_at IS NULL and r.id IN (SELECT rowid FROM records_fts WHERE records_fts MATCH ?1)
+             order by r.date desc, r.id desc",
+            [query],
+        )?;
+        records.truncate(limit);
+        Ok(records)
+    }
+
+    fn months(&self) -> Result<Vec<NaiveDate>> {
+        let mut stmt = self.conn.prepare("select distinct STRFTIME('%m-%Y', date) from records where deleted_at IS NULL order by date desc")?;
+        let dates_iter = stmt.query_map([], |row| {
+            let value: Option<String> = row.get(0)?;
+            Ok(value)
+        })?;
 
-pub fn get_month_year_naive() -> Result<Vec<NaiveDate>> {
-    let conn = get_connection();
+        let mut db_dates = Vec::new();
+        for date in dates_iter {
+            // rows with an unparseable date have no month, they can't get a tab
+            let Some(date) = date? else { continue };
+            let month = NaiveDate::parse_from_str(format!("01-{}", date).as_str(),"%d-%m-%Y")
+                .map_err(|_| BuldakError::InvalidDate(date))?;
+            db_dates.push(month);
+        }
+        Ok(with_current_month(db_dates))
+    }
+
+    fn categories(&self) -> Result<Vec<Category>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, key, name, colour, sort_order, archived FROM categories order by sort_order asc, id asc")?;
+        let categories = stmt.query_map([], |row| {
+            Ok(Category {
+                id: row.get(0)?,
+                key: row.get(1)?,
+                name: row.get(2)?,
+                colour: row.get(3)?,
+                sort_order: row.get(4)?,
+                archived: row.get(5)?,
+            })
+        })?;
+        Ok(categories.collect::<rusqlite::Result<Vec<Category>>>()?)
+    }
+
+    fn add_category(&self, name: &str, colour: &str) -> Result<i64> {
+        let key = self.unique_category_key(name)?;
+        self.conn.execute(
+            "INSERT INTO categories (key, name, colour, sort_order)
+             VALUES (?1, ?2, ?3, (SELECT IFNULL(MAX(sort_order), 0) + 1 FROM categories))",
+            (&key, name.trim(), colour),
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn rename_category(&self, id: i64, name: &str) -> Result<()> {
+        self.conn.execute("UPDATE categories SET name = ?1 WHERE id = ?2", (name.trim(), id))?;
+        Ok(())
+    }
+
+    fn set_category_colour(&self, id: i64, colour: &str) -> Result<()> {
+        self.conn.execute("UPDATE categories SET colour = ?1 WHERE id = ?2", (colour, id))?;
+        Ok(())
+    }
+
+    fn set_category_archived(&self, id: i64, archived: bool) -> Result<()> {
+        self.conn.execute("UPDATE categories SET archived = ?1 WHERE id = ?2", (archived, id))?;
+        Ok(())
+    }
+
+    fn swap_category_order(&self, first: &Category, second: &Category) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("UPDATE categories SET sort_order = ?1 WHERE id = ?2", (second.sort_order, first.id))?;
+        tx.execute("UPDATE categories SET sort_order = ?1 WHERE id = ?2", (first.sort_order, second.id))?;
+        tx.commit()?;
+        Ok(())
+    }
 
-    let mut stmt = conn.prepare("select distinct STRFTIME('%m-%Y', date) from records order by date desc")?;
-    let dates_iter = stmt.query_map([], |row| {
-        let value: String = row.get(0)?;
-        Ok(value)
-    })?;
+    fn budgets_for_month(&self, month: NaiveDate) -> Result<IndexMap<i64, Money>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT b.category_id, b.amount FROM budgets b
+             WHERE b.month = (SELECT MAX(month) FROM budgets WHERE category_id = b.category_id AND month <= ?1)
+               AND b.amount <> 0
+             order by b.category_id")?;
+        let budgets = stmt.query_map([first_of_month(month)], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(budgets.collect::<rusqlite::Result<IndexMap<i64, Money>>>()?)
+    }
+
+    fn set_budget(&self, category_id: i64, month: NaiveDate, amount: Money) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO budgets (category_id, month, amount) VALUES (?1, ?2, ?3)
+             ON CONFLICT (category_id, month) DO UPDATE SET amount = excluded.amount",
+            (category_id, first_of_month(month), amount),
+        )?;
+        Ok(())
+    }
+
+    fn base_currency(&self) -> Result<String> {
+        Ok(self.conn.query_row("SELECT value FROM settings WHERE key = 'base_currency'", [], |row| row.get(0))?)
+    }
+
+    fn set_base_currency(&self, currency: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO settings (key, value) VALUES ('base_currency', ?1)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            [currency],
+        )?;
+        Ok(())
+    }
 
-    let mut db_dates = dates_iter.map(|r| {
-        let date = r.unwrap();
-        NaiveDate::parse_from_str(format!("01-{}", date).as_str(),"%d-%m-%Y").unwrap()
-    }).collect::<Vec<NaiveDate>>();
+    fn setting(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0)).optional()?)
+    }
+
+    fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            [key, value],
+        )?;
+        Ok(())
+    }
 
-    let current_month = Utc::now().with_day(1).unwrap().date_naive();
-    if !db_dates.contains(&current_month) {
-        db_dates.insert(0, current_month);
+    fn exchange_rates(&self) -> Result<Vec<ExchangeRate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, from_currency, to_currency, rate FROM exchange_rates order by date asc, from_currency, to_currency")?;
+        let rates = stmt.query_map([], |row| {
+            Ok(ExchangeRate { date: row.get(0)?, from: row.get(1)?, to: row.get(2)?, rate: row.get(3)? })
+        })?;
+        Ok(rates.collect::<rusqlite::Result<Vec<ExchangeRate>>>()?)
+    }
+
+    fn add_exchange_rates(&self, rates: &[ExchangeRate]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO exchange_rates (date, from_currency, to_currency, rate) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (date, from_currency, to_currency) DO UPDATE SET rate = excluded.rate")?;
+            for rate in rates {
+                stmt.execute((&rate.date, &rate.from, &rate.to, rate.rate))?;
+            }
+        }
+        tx.commit()?;
+        Ok(rates.len())
+    }
+
+    fn recurring_rules(&self) -> Result<Vec<RecurringRule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, comment, schedule, start_date, last_run, currency FROM recurring_rules order by id asc")?;
+        let rules = stmt.query_map([], |row| {
+            Ok(RecurringRule {
+                id: row.get(0)?,
+                amounts: IndexMap::new(),
+                comments: row.get(1)?,
+                schedule: row.get(2)?,
+                start_date: row.get(3)?,
+                last_run: row.get(4)?,
+                currency: row.get(5)?,
+            })
+        })?.collect::<rusqlite::Result<Vec<RecurringRule>>>()?;
+        let mut lines = self.conn.prepare("SELECT category_id, amount FROM recurring_lines WHERE rule_id = ?1 order by category_id")?;
+        rules.into_iter().map(|mut rule| {
+            rule.amounts = lines
+                .query_map([rule.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<IndexMap<i64, Money>>>()?;
+            Ok(rule)
+        }).collect()
+    }
+
+    fn add_recurring_rule(&self, rule: &RecurringRule) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO recurring_rules (comment, schedule, start_date, last_run, currency) VALUES (?1, ?2, ?3, ?4, ?5)",
+            (&rule.comments, &rule.schedule, &rule.start_date, &rule.last_run, &rule.currency),
+        )?;
+        let rule_id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare("INSERT INTO recurring_lines (rule_id, category_id, amount) VALUES (?1, ?2, ?3)")?;
+            for (category_id, amount) in rule.amounts.iter().filter(|(_, amount)| !amount.is_zero()) {
+                stmt.execute((rule_id, category_id, amount))?;
+            }
+        }
+        tx.commit()?;
+        Ok(rule_id)
+    }
+
+    fn delete_recurring_rule(&self, id: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM recurring_lines WHERE rule_id = ?1", [id])?;
+        tx.execute("DELETE FROM recurring_rules WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn materialize_recurring(&self, today: NaiveDate) -> Result<usize> {
+        let rules = self.recurring_rules()?;
+        let tx = self.conn.unchecked_transaction()?;
+        let mut created = 0;
+        for rule in rules {
+            for date in rule.due_dates(today) {
+                Self::insert_record(&tx, &rule.record_on(date))?;
+                created += 1;
+            }
+            tx.execute("UPDATE recurring_rules SET last_run = ?1 WHERE id = ?2", (today, rule.id))?;
+        }
+        tx.commit()?;
+        Ok(created)
     }
-    Ok(db_dates)
 }
 
-fn get_connection() -> Connection {
-     Connection::open(SQL_FILENAME).unwrap()
+/// Turns what the user typed into an FTS5 query where every word has to match the start of a
+/// word, `Φαρμ mar` -> `"φαρμ"* "mar"*`. Quotes keep FTS5 operators in the input literal.
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|word| fold_accents(word.trim_start_matches('#')).replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    if terms.is_empty() { None } else { Some(terms.join(" ")) }
 }
 
-pub fn init_db(){
-    let _ = fs::copy(SQL_FILENAME, BACKUP_FILENAME);
-    let conn = Connection::open(SQL_FILENAME).unwrap();
-    let _ =conn.execute(
-        "CREATE TABLE if not exists records (
-                id    INTEGER PRIMARY KEY,
-                store  FLOAT,
-                beer  FLOAT,
-                allos  FLOAT,
-                comment  TEXT,
-                date  TEXT
-            )",
-        (),
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::period::Period;
+    use crate::repository::memory::InMemoryRepository;
+
+    fn repository() -> SqliteRepository {
+        SqliteRepository::from_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_fresh_database_matches_in_memory_seed() {
+        let (fresh, memory) = (repository(), InMemoryRepository::new());
+        assert_eq!(format!("{:?}", fresh.categories().unwrap()), format!("{:?}", memory.categories().unwrap()));
+        assert_eq!(fresh.base_currency().unwrap(), memory.base_currency().unwrap());
+    }
+
+    fn record(date: &str, amounts: &[(i64, &str)], comments: &str) -> Record {
+        Record {
+            id: 0,
+            amounts: amounts.iter().map(|(id, a)| (*id, a.parse().unwrap())).collect(),
+            comments: comments.to_string(),
+            date: date.parse().unwrap(),
+            currency: "EUR".to_string(),
+            rate: None,
+        }
+    }
+
+    #[test]
+    fn test_save_update_delete_round_trip() {
+        let repo = repository();
+        let march_tab = Period::Month("2025-03-01".parse().unwrap());
+        let march: NaiveDate = march_tab.start();
+        let id = repo.save_record(&record("2025-03-02", &[(1, "12.50"), (2, "0")], "lidl")).unwrap();
+        repo.save_record(&record("2025-04-01", &[(3, "1")], "april")).unwrap();
+
+        let holder = repo.records_for_period(&march_tab).unwrap();
+        assert_eq!(holder.records.len(), 1);
+        assert_eq!(holder.records[0].amounts.len(), 1, "zero lines are not stored");
+        assert_eq!(holder.total(1), "12.50".parse().unwrap());
+
+        let mut edited = holder.records[0].clone();
+        edited.amounts.insert(2, "3".parse().unwrap());
+        repo.update_record(&edited).unwrap();
+        assert_eq!(repo.records_for_period(&march_tab).unwrap().all_total, "15.50".parse().unwrap());
+
+        repo.delete_record(id).unwrap();
+        assert!(repo.records_for_period(&march_tab).unwrap().records.is_empty());
+        assert!(!repo.months().unwrap().contains(&march));
+        repo.restore_record(id).unwrap();
+        assert_eq!(repo.records_for_period(&march_tab).unwrap().records.len(), 1);
+        assert!(repo.months().unwrap().contains(&march));
+    }
+
+    #[test]
+    fn test_add_category_gets_unique_key() {
+        let repo = repository();
+        repo.add_category("Store", "red").unwrap();
+        let categories = repo.categories().unwrap();
+        assert_eq!(categories.last().unwrap().key, "store-2");
+        assert_eq!(categories.last().unwrap().sort_order, 4);
+    }
+
+    #[test]
+    fn test_records_between_with_filters() {
+        let repo = repository();
+        let d = |s: &str| s.parse::<NaiveDate>().unwrap();
+        repo.save_record(&record("2025-01-31", &[(1, "40")], "Lidl")).unwrap();
+        repo.save_record(&record("2025-02-10", &[(1, "8"), (2, "3")], "ΜΠΥΡΑ περιπτερο")).unwrap();
+        repo.save_record(&record("2025-03-31", &[(3, "120")], "lidl rent")).unwrap();
+        repo.save_record(&record("2025-04-01", &[(1, "1")], "lidl")).unwrap();
+
+        let all = RecordFilter::default();
+        let q1 = repo.records_between(d("2025-01-01"), d("2025-03-31"), &all).unwrap();
+        assert_eq!(q1.records.len(), 3);
+        assert_eq!(q1.all_total, "171".parse().unwrap());
+
+        let beer = RecordFilter { category: Some(2), ..Default::default() };
+        assert_eq!(repo.records_between(d("2025-01-01"), d("2025-12-31"), &beer).unwrap().records.len(), 1);
+
+        let text = RecordFilter { comment: Some("μπυρα".to_string()), ..Default::default() };
+        assert_eq!(repo.records_between(d("2025-01-01"), d("2025-12-31"), &text).unwrap().records[0].date, d("2025-02-10"));
+
+        let lidl_range = RecordFilter {
+            comment: Some("LIDL".to_string()),
+            min_amount: Some("10".parse().unwrap()),
+            max_amount: Some("100".parse().unwrap()),
+            ..Default::default()
+        };
+        let found = repo.records_between(d("2025-01-01"), d("2025-12-31"), &lidl_range).unwrap();
+        assert_eq!(found.records.iter().map(|r| r.date).collect::<Vec<_>>(), vec![d("2025-01-31")]);
+    }
+
+    #[test]
+    fn test_range_query_uses_date_index() {
+        let repo = repository();
+        let plan: String = repo.conn.query_row(
+            "EXPLAIN QUERY PLAN SELECT id FROM records WHERE date >= ?1 and date <= ?2",
+            ["2025-01-01", "2025-03-31"],
+            |row| row.get(3),
+        ).unwrap();
+        assert!(plan.contains("records_date"), "{}", plan);
+    }
+
+    #[test]
+    fn test_materialize_recurring_once() {
+        let repo = repository();
+        let d = |s: &str| s.parse::<NaiveDate>().unwrap();
+        let rule = RecurringRule {
+            id: 0,
+            amounts: [(3, "450".parse().unwrap())].into_iter().collect(),
+            comments: "ενοίκιο".to_string(),
+            schedule: "monthly 1".parse().unwrap(),
+            start_date: d("2025-01-15"),
+            last_run: None,
+            currency: "EUR".to_string(),
+        };
+        let id = repo.add_recurring_rule(&rule).unwrap();
+        assert_eq!(repo.recurring_rules().unwrap()[0].amounts, rule.amounts);
+
+        assert_eq!(repo.materialize_recurring(d("2025-03-18")).unwrap(), 2);
+        assert_eq!(repo.materialize_recurring(d("2025-03-20")).unwrap(), 0, "already created");
+        assert_eq!(repo.recurring_rules().unwrap()[0].last_run, Some(d("2025-03-20")));
+        let created = repo.records_between(d("2025-01-01"), d("2025-12-31"), &RecordFilter::default()).unwrap();
+        assert_eq!(created.records.iter().map(|r| r.date).collect::<Vec<_>>(), vec![d("2025-02-01"), d("2025-03-01")]);
+        assert_eq!(created.total(3), "900".parse().unwrap());
+
+        repo.delete_recurring_rule(id).unwrap();
+        assert!(repo.recurring_rules().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_budgets_carry_over() {
+        let repo = repository();
+        let d = |s: &str| s.parse::<NaiveDate>().unwrap();
+        let m = |s: &str| s.parse::<Money>().unwrap();
+        repo.set_budget(1, d("2025-01-01"), m("300")).unwrap();
+        repo.set_budget(2, d("2025-02-10"), m("40")).unwrap();
+        repo.set_budget(1, d("2025-04-01"), m("350")).unwrap();
+        repo.set_budget(2, d("2025-05-01"), Money::ZERO).unwrap();
+
+        assert!(repo.budgets_for_month(d("2024-12-01")).unwrap().is_empty());
+        let march = repo.budgets_for_month(d("2025-03-01")).unwrap();
+        assert_eq!((march.get(&1), march.get(&2)), (Some(&m("300")), Some(&m("40"))));
+        let may = repo.budgets_for_month(d("2025-05-01")).unwrap();
+        assert_eq!(may.into_iter().collect::<Vec<_>>(), vec![(1, m("350"))]);
+
+        repo.set_budget(1, d("2025-04-20"), m("360")).unwrap();
+        assert_eq!(repo.budgets_for_month(d("2025-06-01")).unwrap()[&1], m("360"));
+    }
+
+    #[test]
+    fn test_totals_converted_to_base_currency() {
+        let repo = repository();
+        let d = |s: &str| s.parse::<NaiveDate>().unwrap();
+        let mut dollars = record("2025-03-10", &[(1, "10")], "");
+        dollars.currency = "USD".to_string();
+        repo.save_record(&dollars).unwrap();
+        repo.save_record(&record("2025-03-11", &[(1, "5")], "")).unwrap();
+        let march = Period::Month(d("2025-03-01"));
+
+        let holder = repo.records_for_period(&march).unwrap();
+        assert_eq!(holder.total(1), "5".parse().unwrap());
+        assert_eq!(holder.missing_rates, vec!["USD".to_string()]);
+
+        repo.add_exchange_rates(&[
+            ExchangeRate::new(d("2025-03-01"), "USD", "EUR", 0.9).unwrap(),
+            ExchangeRate::new(d("2025-03-01"), "EUR", "GBP", 0.8).unwrap(),
+        ]).unwrap();
+        let holder = repo.records_for_period(&march).unwrap();
+        assert_eq!(holder.records[0].currency, "USD");
+        assert_eq!(holder.records[0].get_day_summary(), "10".parse().unwrap(), "original amount kept");
+        assert_eq!(holder.total(1), "14".parse().unwrap());
+
+        repo.set_base_currency("GBP").unwrap();
+        let holder = repo.records_for_period(&march).unwrap();
+        assert_eq!((holder.base_currency.as_str(), holder.all_total), ("GBP", "4".parse().unwrap()));
+        assert_eq!(holder.missing_rates, vec!["USD".to_string()]);
+    }
+
+    #[test]
+    fn test_tags_follow_comments() {
+        let repo = repository();
+        let d = |s: &str| s.parse::<NaiveDate>().unwrap();
+        let id = repo.save_record(&record("2025-03-02", &[(1, "10")], "#Holiday ferry #food")).unwrap();
+        repo.save_record(&record("2025-03-03", &[(2, "4")], "beach #holiday")).unwrap();
+        repo.save_record(&record("2025-03-04", &[(3, "100")], "rent")).unwrap();
+        let holiday = RecordFilter { tag: Some("holiday".to_string()), ..Default::default() };
+
+        let tagged = repo.records_between(d("2025-03-01"), d("2025-03-31"), &holiday).unwrap();
+        assert_eq!(tagged.all_total, "14".parse().unwrap());
+        let totals = repo.tag_totals(d("2025-03-01"), d("2025-03-31")).unwrap();
+        assert_eq!(totals.into_iter().collect::<Vec<_>>(), [
+            ("holiday".to_string(), "14".parse().unwrap()),
+            ("food".to_string(), "10".parse().unwrap()),
+        ]);
+
+        let mut edited = tagged.records[0].clone();
+        edited.comments = "ferry".to_string();
+        repo.update_record(&edited).unwrap();
+        assert_eq!(repo.records_between(d("2025-03-01"), d("2025-03-31"), &holiday).unwrap().records.len(), 1);
+        repo.delete_record(id).unwrap();
+        assert!(!repo.tag_totals(d("2025-03-01"), d("2025-03-31")).unwrap().contains_key("food"));
+    }
+
+    #[test]
+    fn test_existing_comments_get_tagged_on_upgrade() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        // back to the version just before the first migration that reindexes records
+        let before_tags = MIGRATIONS.iter().position(|m| m.reindex).unwrap();
+        conn.execute_batch(&format!(
+            "DROP TABLE record_tags;
+             DROP TABLE tags;
+             DROP TABLE records_fts;
+             INSERT INTO records (id, comment, date) VALUES (1, 'trip #Greece', '2025-03-02');
+             INSERT INTO record_lines (record_id, category_id, amount) VALUES (1, 1, 500);
+             PRAGMA user_version = {};",
+            before_tags,
+        )).unwrap();
+
+        let repo = SqliteRepository::from_connection(conn).unwrap();
+        let greece = RecordFilter { tag: Some("greece".to_string()), ..Default::default() };
+        let d = |s: &str| s.parse::<NaiveDate>().unwrap();
+        assert_eq!(repo.records_between(d("2025-03-01"), d("2025-03-31"), &greece).unwrap().records.len(), 1);
+        assert_eq!(repo.search("trip", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_across_months() {
+        let repo = repository();
+        repo.save_record(&record("2025-03-14", &[(3, "40")], "Φαρμακείο ασπιρίνες #υγεία")).unwrap();
+        let old = repo.save_record(&record("2024-11-02", &[(3, "12")], "φαρμακείο")).unwrap();
+        repo.save_record(&record("2025-03-15", &[(1, "5")], "lidl \"special\" OR")).unwrap();
+
+        let found = repo.search("φαρμακ", 10).unwrap();
+        assert_eq!(found.iter().map(|r| r.date.to_string()).collect::<Vec<_>>(), ["2025-03-14", "2024-11-02"]);
+        assert_eq!(repo.search("ΦΑΡΜΑΚΕΙΟ #υγεια", 10).unwrap().len(), 1, "case, accents and tags");
+        assert_eq!(repo.search("\"special OR", 10).unwrap().len(), 1, "operators are plain words");
+        assert!(repo.search("  ", 10).unwrap().is_empty());
+
+        repo.delete_record(old).unwrap();
+        assert_eq!(repo.search("φαρμακειο", 10).unwrap().len(), 1);
+        let mut edited = repo.search("lidl", 10).unwrap()[0].clone();
+        edited.comments = "aldi".to_string();
+        repo.update_record(&edited).unwrap();
+        assert!(repo.search("lidl", 10).unwrap().is_empty());
+    }
 }
@@ -0,0 +1,74 @@
+use std::fmt;
+use crate::backup::BackupError;
+use crate::migrations::MigrationError;
+
+/// Every failure the app can recover from; shown in the status bar by [`crate::App`].
+#[derive(Debug)]
+pub enum BuldakError {
+    Db(rusqlite::Error),
+    Migration(MigrationError),
+    Backup(BackupError),
+    Io(std::io::Error),
+    /// An amount field could not be turned into money.
+    InvalidAmount { input: String, reason: String },
+    InvalidDate(String),
+    /// No category has this key.
+    UnknownCategory(String),
+    InvalidSchedule(String),
+    InvalidCurrency(String),
+    InvalidRate(String),
+    Config(String),
+    /// `buldak export --check` found the file differs from a fresh export from this line on.
+    ExportChanged { path: String, line: usize },
+}
+
+pub type Result<T> = std::result::Result<T, BuldakError>;
+
+impl fmt::Display for BuldakError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuldakError::Db(err) => write!(f, "database error: {}", err),
+            BuldakError::Migration(err) => write!(f, "{}", err),
+            BuldakError::Backup(err) => write!(f, "{}", err),
+            BuldakError::Io(err) => write!(f, "{}", err),
+            BuldakError::InvalidAmount { input, reason } => write!(f, "invalid amount '{}': {}", input, reason),
+            BuldakError::InvalidDate(input) => write!(f, "invalid date '{}'", input),
+            BuldakError::UnknownCategory(key) => write!(f, "unknown category '{}'", key),
+            BuldakError::InvalidSchedule(input) => write!(
+                f,
+                "invalid schedule '{}', expected 'monthly 15', 'weekly mon' or 'yearly 14/03'",
+                input
+            ),
+            BuldakError::InvalidCurrency(code) => write!(f, "invalid currency '{}', expected a code such as EUR", code),
+            BuldakError::InvalidRate(rate) => write!(f, "invalid exchange rate '{}'", rate),
+            BuldakError::Config(message) => write!(f, "{}", message),
+            BuldakError::ExportChanged { path, line } => write!(f, "{} differs from a fresh export at line {}", path, line),
+        }
+    }
+}
+
+impl std::error::Error for BuldakError {}
+
+impl From<rusqlite::Error> for BuldakError {
+    fn from(err: rusqlite::Error) -> Self {
+        BuldakError::Db(err)
+    }
+}
+
+impl From<MigrationError> for BuldakError {
+    fn from(err: MigrationError) -> Self {
+        BuldakError::Migration(err)
+    }
+}
+
+impl From<BackupError> for BuldakError {
+    fn from(err: BackupError) -> Self {
+        BuldakError::Backup(err)
+    }
+}
+
+impl From<std::io::Error> for BuldakError {
+    fn from(err: std::io::Error) -> Self {
+        BuldakError::Io(err)
+    }
+}
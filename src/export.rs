@@ -0,0 +1,212 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use crate::error::{BuldakError, Result};
+use crate::model::RecordsHolder;
+use crate::money::Money;
+use crate::period::Period;
+use crate::repository::Repository;
+
+/// Keys of the saved defaults in the settings, changed with `buldak export ... --save`.
+const DELIMITER_SETTING: &str = "csv_delimiter";
+const DECIMAL_SETTING: &str = "csv_decimal";
+const LANGUAGE_SETTING: &str = "csv_language";
+
+/// Language of the CSV header row. Greek uses the category names, English their keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderLanguage {
+    Greek,
+    English,
+}
+
+impl FromStr for HeaderLanguage {
+    type Err = BuldakError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "el" | "gr" => Ok(HeaderLanguage::Greek),
+            "en" => Ok(HeaderLanguage::English),
+            _ => Err(BuldakError::Config(format!("unknown header language '{}', expected el or en", s))),
+        }
+    }
+}
+
+impl fmt::Display for HeaderLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderLanguage::Greek => write!(f, "el"),
+            HeaderLanguage::English => write!(f, "en"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    /// Replaces the `.` of amounts, e.g. `,` for spreadsheets in Greek.
+    pub decimal: char,
+    pub language: HeaderLanguage,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: ',', decimal: '.', language: HeaderLanguage::Greek }
+    }
+}
+
+impl CsvOptions {
+    /// The saved defaults, falling back to [`CsvOptions::default`] for the ones never saved.
+    pub fn load(repo: &dyn Repository) -> Result<Self> {
+        let mut options = CsvOptions::default();
+        for name in ["delimiter", "decimal", "lang"] {
+            if let Some(value) = repo.setting(setting_key(name))? {
+                options.set(name, &value)?;
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn save(&self, repo: &dyn Repository) -> Result<()> {
+        repo.set_setting(DELIMITER_SETTING, &self.delimiter.to_string())?;
+        repo.set_setting(DECIMAL_SETTING, &self.decimal.to_string())?;
+        repo.set_setting(LANGUAGE_SETTING, &self.language.to_string())
+    }
+
+    /// Changes one option by its command line name; `tab` stands for a tab delimiter.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "delimiter" => self.delimiter = single_char(name, value)?,
+            "decimal" => self.decimal = single_char(name, value)?,
+            "lang" => self.language = value.parse()?,
+            _ => return Err(BuldakError::Config(format!("unknown CSV option '{}'", name))),
+        }
+        Ok(())
+    }
+}
+
+fn setting_key(name: &str) -> &'static str {
+    match name {
+        "delimiter" => DELIMITER_SETTING,
+        "decimal" => DECIMAL_SETTING,
+        _ => LANGUAGE_SETTING,
+    }
+}
+
+/// A delimiter or separator given on the command line.
+pub fn single_char(name: &str, value: &str) -> Result<char> {
+    let value = if value == "tab" { "\t" } else { value };
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != '"' && c != '\n' => Ok(c),
+        _ => Err(BuldakError::Config(format!("--{} expects a single character, got '{}'", name, value))),
+    }
+}
+
+/// Default file name of the in-app export, e.g. `buldak-Mar-2025.csv`.
+pub fn export_file_name(period: &Period) -> String {
+    format!("buldak-{}.csv", period)
+}
+
+/// Writes one row per record with the record id in front. Amounts are plain numbers in
+/// the record's own currency, followed by the currency and its rate to the base currency.
+pub fn write_csv<W: Write>(holder: &RecordsHolder, options: &CsvOptions, mut out: W) -> Result<()> {
+    let mut header = match options.language {
+        HeaderLanguage::Greek => vec!["Κωδικός".to_string(), "Ημερομηνία".to_string()],
+        HeaderLanguage::English => vec!["id".to_string(), "date".to_string()],
+    };
+    header.extend(holder.categories.iter().map(|c| match options.language {
+        HeaderLanguage::Greek => c.name.clone(),
+        HeaderLanguage::English => c.key.clone(),
+    }));
+    header.extend(match options.language {
+        HeaderLanguage::Greek => ["Σύνολο σε μέρα", "Νόμισμα", "Ισοτιμία", "Σχόλια"],
+        HeaderLanguage::English => ["day total", "currency", "rate", "comments"],
+    }.map(String::from));
+    write_row(&mut out, &header, options.delimiter)?;
+
+    let number = |amount: Money| amount.to_string().replace('.', &options.decimal.to_string());
+    for record in &holder.records {
+        let mut row = vec![record.id.to_string(), record.date.format("%Y-%m-%d").to_string()];
+        row.extend(holder.categories.iter().map(|c| number(record.amount(c.id))));
+        row.push(number(record.get_day_summary()));
+        row.push(record.currency.clone());
+        row.push(record.rate.map_or(String::new(), |rate| rate.to_string().replace('.', &options.decimal.to_string())));
+        row.push(record.comments.clone());
+        write_row(&mut out, &row, options.delimiter)?;
+    }
+    Ok(())
+}
+
+fn write_row<W: Write>(out: &mut W, fields: &[String], delimiter: char) -> Result<()> {
+    let line = fields.iter()
+        .map(|field| {
+            if field.contains(delimiter) || field.contains('"') || field.contains('\n') {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(&delimiter.to_string());
+    writeln!(out, "{}", line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::currency::ExchangeRate;
+    use crate::model::Record;
+    use crate::repository::memory::InMemoryRepository;
+
+    fn holder(repo: &InMemoryRepository) -> RecordsHolder {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 2).unwrap();
+        let amounts = [(1, "12.50".parse().unwrap()), (2, "3".parse().unwrap())].into_iter().collect();
+        repo.save_record(&Record { id: 0, amounts, comments: "lidl; \"big\" shop".to_string(), date, currency: "EUR".to_string(), rate: None }).unwrap();
+        repo.records_for_period(&Period::Month(date)).unwrap()
+    }
+
+    #[test]
+    fn test_write_csv_with_options() {
+        let repo = InMemoryRepository::new();
+        let mut out = Vec::new();
+        write_csv(&holder(&repo), &CsvOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+            "Κωδικός,Ημερομηνία,Προϊόντα,Μπύρα,Αλλος,Σύνολο σε μέρα,Νόμισμα,Ισοτιμία,Σχόλια\n\
+             1,2025-03-02,12.50,3.00,0.00,15.50,EUR,1,\"lidl; \"\"big\"\" shop\"\n");
+
+        let options = CsvOptions { delimiter: ';', decimal: ',', language: HeaderLanguage::English };
+        let mut out = Vec::new();
+        write_csv(&holder(&InMemoryRepository::new()), &options, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+            "id;date;store;beer;allos;day total;currency;rate;comments\n\
+             1;2025-03-02;12,50;3,00;0,00;15,50;EUR;1;\"lidl; \"\"big\"\" shop\"\n");
+    }
+
+    #[test]
+    fn test_foreign_currency_amounts_stay_numbers() {
+        let repo = InMemoryRepository::new();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 2).unwrap();
+        repo.add_exchange_rates(&[ExchangeRate::new(date, "USD", "EUR", 0.925).unwrap()]).unwrap();
+        let amounts = [(1, "10".parse().unwrap())].into_iter().collect();
+        repo.save_record(&Record { id: 0, amounts, comments: String::new(), date, currency: "USD".to_string(), rate: None }).unwrap();
+        let mut out = Vec::new();
+        let options = CsvOptions { language: HeaderLanguage::English, ..Default::default() };
+        write_csv(&repo.records_for_period(&Period::Month(date)).unwrap(), &options, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().nth(1), Some("1,2025-03-02,10.00,0.00,0.00,10.00,USD,0.925,"));
+    }
+
+    #[test]
+    fn test_options_saved_in_settings() {
+        let repo = InMemoryRepository::new();
+        assert_eq!(CsvOptions::load(&repo).unwrap(), CsvOptions::default());
+        let mut options = CsvOptions::default();
+        options.set("delimiter", "tab").unwrap();
+        options.set("lang", "EN").unwrap();
+        options.save(&repo).unwrap();
+        assert_eq!(CsvOptions::load(&repo).unwrap(), CsvOptions { delimiter: '\t', decimal: '.', language: HeaderLanguage::English });
+        assert!(options.set("decimal", ",,").is_err());
+        assert!(options.set("lang", "fr").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+use crate::money::Money;
+
+/// Why an amount expression was rejected. Positions are 1-based character columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionError {
+    /// A number or `(` was expected, e.g. after a trailing `+`.
+    ExpectedNumber { position: usize },
+    UnexpectedChar { position: usize, found: char },
+    /// A number such as `1.2.3`.
+    InvalidNumber { position: usize, text: String },
+    /// The `(` at `position` is never closed.
+    UnclosedParen { position: usize },
+    /// A `)` without a `(` before it.
+    UnmatchedParen { position: usize },
+    /// The `/` at `position` divides by zero.
+    DivisionByZero { position: usize },
+    TooLarge,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::ExpectedNumber { position } => write!(f, "expected a number at {}", position),
+            ExpressionError::UnexpectedChar { position, found } => write!(f, "unexpected '{}' at {}", found, position),
+            ExpressionError::InvalidNumber { position, text } => write!(f, "'{}' at {} is not a number", text, position),
+            ExpressionError::UnclosedParen { position } => write!(f, "'(' at {} is never closed", position),
+            ExpressionError::UnmatchedParen { position } => write!(f, "')' at {} has no '('", position),
+            ExpressionError::DivisionByZero { position } => write!(f, "division by zero at {}", position),
+            ExpressionError::TooLarge => write!(f, "amount too large"),
+        }
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+type Result<T> = std::result::Result<T, ExpressionError>;
+
+/// Evaluates an amount such as `12.5+3*2`, `-(5+10)/3` or `40-10%`, rounding the result
+/// to cents only at the end. A percentage added to or taken from something is a share of
+/// it, so `40-10%` is 36; anywhere else `10%` is just 0.1.
+pub fn evaluate(input: &str) -> Result<Money> {
+    let mut parser = Parser { chars: input.chars().collect(), position: 0 };
+    let value = parser.expression()?;
+    parser.skip_spaces();
+    match parser.peek() {
+        None => value.to_money(),
+        Some(')') => Err(ExpressionError::UnmatchedParen { position: parser.position + 1 }),
+        Some(found) => Err(ExpressionError::UnexpectedChar { position: parser.position + 1, found }),
+    }
+}
+
+/// An exact fraction, so that `10/3*3` is 10 and not 9.99.
+#[derive(Debug, Clone, Copy)]
+struct Ratio {
+    num: i128,
+    den: i128,
+}
+
+impl Ratio {
+    fn new(num: i128, den: i128) -> Self {
+        let sign = if den < 0 { -1 } else { 1 };
+        let divisor = gcd(num, den).max(1);
+        Ratio { num: sign * num / divisor, den: sign * den / divisor }
+    }
+
+    fn add(self, other: Ratio) -> Result<Self> {
+        let num = self.num.checked_mul(other.den)
+            .zip(other.num.checked_mul(self.den))
+            .and_then(|(a, b)| a.checked_add(b));
+        let den = self.den.checked_mul(other.den);
+        num.zip(den).map(|(num, den)| Ratio::new(num, den)).ok_or(ExpressionError::TooLarge)
+    }
+
+    fn mul(self, other: Ratio) -> Result<Self> {
+        let num = self.num.checked_mul(other.num);
+        let den = self.den.checked_mul(other.den);
+        num.zip(den).map(|(num, den)| Ratio::new(num, den)).ok_or(ExpressionError::TooLarge)
+    }
+
+    fn percent(self) -> Result<Self> {
+        self.mul(Ratio::new(1, 100))
+    }
+
+    fn neg(self) -> Self {
+        Ratio { num: -self.num, den: self.den }
+    }
+
+    /// Rounds half away from zero to whole cents.
+    fn to_money(self) -> Result<Money> {
+        let cents = self.num.checked_abs()
+            .and_then(|num| num.checked_mul(200))
+            .and_then(|num| num.checked_add(self.den))
+            .zip(self.den.checked_mul(2))
+            .and_then(|(num, divisor)| i64::try_from(num / divisor).ok())
+            .ok_or(ExpressionError::TooLarge)?;
+        Some(Money::from_cents(if self.num < 0 { -cents } else { cents }))
+            .filter(|money| money.in_range())
+            .ok_or(ExpressionError::TooLarge)
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// A term and whether it was a bare percentage, which `+` and `-` treat as a share.
+struct Term {
+    value: Ratio,
+    percent: bool,
+}
+
+/// Recursive descent over `expression := term (('+' | '-') term)*`,
+/// `term := unary (('*' | '/') unary)*`, `unary := ('-' | '+') unary | primary '%'?`
+/// and `primary := number | '(' expression ')'`.
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Skips spaces and consumes `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_spaces();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Result<Ratio> {
+        let first = self.term()?;
+        let mut value = if first.percent { first.value.percent()? } else { first.value };
+        loop {
+            let sign = if self.eat('+') {
+                1
+            } else if self.eat('-') {
+                -1
+            } else {
+                return Ok(value);
+            };
+            let term = self.term()?;
+            let operand = if term.percent { value.mul(term.value)?.percent()? } else { term.value };
+            value = value.add(if sign < 0 { operand.neg() } else { operand })?;
+        }
+    }
+
+    fn term(&mut self) -> Result<Term> {
+        let mut term = self.unary()?;
+        loop {
+            let divide = if self.eat('*') {
+                false
+            } else if self.eat('/') {
+                true
+            } else {
+                return Ok(term);
+            };
+            let operator_position = self.position;
+            let factor = self.unary()?;
+            let factor = if factor.percent { factor.value.percent()? } else { factor.value };
+            let left = if term.percent { term.value.percent()? } else { term.value };
+            term = Term {
+                value: if divide {
+                    if factor.num == 0 {
+                        return Err(ExpressionError::DivisionByZero { position: operator_position });
+                    }
+                    left.mul(Ratio::new(factor.den, factor.num))?
+                } else {
+                    left.mul(factor)?
+                },
+                percent: false,
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Term> {
+        if self.eat('-') {
+            let term = self.unary()?;
+            return Ok(Term { value: term.value.neg(), percent: term.percent });
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        let value = self.primary()?;
+        Ok(Term { value, percent: self.eat('%') })
+    }
+
+    fn primary(&mut self) -> Result<Ratio> {
+        self.skip_spaces();
+        let start = self.position;
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let value = self.expression()?;
+                if !self.eat(')') {
+                    self.skip_spaces();
+                    return match self.peek() {
+                        None => Err(ExpressionError::UnclosedParen { position: start + 1 }),
+                        Some(found) => Err(ExpressionError::UnexpectedChar { position: self.position + 1, found }),
+                    };
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.position += 1;
+                }
+                let text: String = self.chars[start..self.position].iter().collect();
+                parse_number(&text).ok_or(ExpressionError::InvalidNumber { position: start + 1, text })
+            }
+            Some(')') => Err(ExpressionError::UnmatchedParen { position: start + 1 }),
+            Some(c) if "+-*/%".contains(c) => Err(ExpressionError::ExpectedNumber { position: start + 1 }),
+            Some(found) => Err(ExpressionError::UnexpectedChar { position: start + 1, found }),
+            None => Err(ExpressionError::ExpectedNumber { position: start + 1 }),
+        }
+    }
+}
+
+/// `12`, `12.5` or `.5`, kept exact.
+fn parse_number(text: &str) -> Option<Ratio> {
+    let (int_part, frac_part) = text.split_once('.').unwrap_or((text, ""));
+    if (int_part.is_empty() && frac_part.is_empty()) || frac_part.contains('.') || int_part.len() + frac_part.len() > 30 {
+        return None;
+    }
+    let digits: i128 = format!("{}{}", int_part, frac_part).parse().ok()?;
+    Some(Ratio::new(digits, 10i128.pow(frac_part.len() as u32)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(amount: &str) -> Money {
+        amount.parse().unwrap()
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        assert_eq!(evaluate("2+3*4").unwrap(), m("14"));
+        assert_eq!(evaluate("(2+3)*4").unwrap(), m("20"));
+        assert_eq!(evaluate("10-4-3").unwrap(), m("3"));
+        assert_eq!(evaluate("12/4/3").unwrap(), m("1"));
+        assert_eq!(evaluate("10/3*3").unwrap(), m("10"));
+        assert_eq!(evaluate("10/3").unwrap(), m("3.33"));
+        assert_eq!(evaluate("2/3").unwrap(), m("0.67"));
+        assert_eq!(evaluate("((1+2)*(3-1))/ 4").unwrap(), m("1.5"));
+        assert_eq!(evaluate(" 1.5 * 2 ").unwrap(), m("3"));
+    }
+
+    #[test]
+    fn test_unary_minus_and_percentages() {
+        assert_eq!(evaluate("-5+10").unwrap(), m("5"));
+        assert_eq!(evaluate("-(5+10)").unwrap(), m("-15"));
+        assert_eq!(evaluate("2*-3").unwrap(), m("-6"));
+        assert_eq!(evaluate("--2").unwrap(), m("2"));
+        assert_eq!(evaluate("40-10%").unwrap(), m("36"));
+        assert_eq!(evaluate("40+10%").unwrap(), m("44"));
+        assert_eq!(evaluate("(20+20)-10%").unwrap(), m("36"));
+        assert_eq!(evaluate("50*10%").unwrap(), m("5"));
+        assert_eq!(evaluate("10%").unwrap(), m("0.1"));
+        assert_eq!(evaluate("-0.005").unwrap(), m("-0.01"));
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        assert_eq!(evaluate("10+"), Err(ExpressionError::ExpectedNumber { position: 4 }));
+        assert_eq!(evaluate("10+*2"), Err(ExpressionError::ExpectedNumber { position: 4 }));
+        assert_eq!(evaluate("12+abc"), Err(ExpressionError::UnexpectedChar { position: 4, found: 'a' }));
+        assert_eq!(evaluate("1.2.3"), Err(ExpressionError::InvalidNumber { position: 1, text: "1.2.3".to_string() }));
+        assert_eq!(evaluate("2*(3+4"), Err(ExpressionError::UnclosedParen { position: 3 }));
+        assert_eq!(evaluate("2+3)"), Err(ExpressionError::UnmatchedParen { position: 4 }));
+        assert_eq!(evaluate("5/(2-2)"), Err(ExpressionError::DivisionByZero { position: 2 }));
+        assert_eq!(evaluate("5 5"), Err(ExpressionError::UnexpectedChar { position: 3, found: '5' }));
+        assert_eq!(evaluate("99999999999999999999*99999999999999999999"), Err(ExpressionError::TooLarge));
+        assert_eq!(evaluate("1/9999999999999999999/9999999999999999999"), Err(ExpressionError::TooLarge));
+        assert_eq!(evaluate("10000000000*2"), Err(ExpressionError::TooLarge));
+        assert_eq!(evaluate("10+").unwrap_err().to_string(), "expected a number at 4");
+    }
+}
@@ -0,0 +1,348 @@
+use chrono::NaiveDate;
+use crate::error::{BuldakError, Result};
+use crate::currency::normalize_currency;
+use crate::input_validator::convert_to_money;
+use crate::locale::Locale;
+use crate::model::{Category, Record, RecordFilter};
+use crate::repository::Repository;
+
+/// How `buldak import` reads a file.
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub delimiter: char,
+    /// Decimal separator of the amounts, `,` turns `1.234,50` into `1234.50`.
+    pub decimal: char,
+    /// chrono format of the date column.
+    pub date_format: String,
+    /// Currency of the rows without a currency column, the base currency when `None`.
+    pub currency: Option<String>,
+    /// Whether the first row holds column names.
+    pub header: bool,
+    /// `(target, column)` pairs: target is `date`, `comment`, `currency` or a category key, column a
+    /// header name or a 1-based number. Columns named like a target are mapped without one.
+    pub mapping: Vec<(String, String)>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            delimiter: ',',
+            decimal: '.',
+            date_format: "%Y-%m-%d".to_string(),
+            currency: None,
+            header: true,
+            mapping: vec![],
+        }
+    }
+}
+
+/// Which column feeds which field, as 0-based indexes.
+#[derive(Debug, PartialEq)]
+struct Mapping {
+    date: usize,
+    comment: Option<usize>,
+    currency: Option<usize>,
+    /// `(category id, column)` in category display order.
+    amounts: Vec<(i64, usize)>,
+}
+
+/// One data row of the file and what importing it would do.
+#[derive(Debug)]
+pub struct ImportRow {
+    /// 1-based line in the file, for messages.
+    pub line: usize,
+    pub record: Result<Record>,
+    /// Same day, currency and amounts as a record already stored or an earlier row.
+    pub duplicate: bool,
+}
+
+/// Splits CSV text into rows of fields. Quoted fields may hold the delimiter, doubled quotes
+/// and line breaks. Returns the line each row starts on with it.
+pub fn parse_csv(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut row_line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            '\n' if quoted => {
+                line += 1;
+                field.push(c);
+            }
+            '\r' if !quoted => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                if row.iter().any(|f| !f.trim().is_empty()) {
+                    rows.push((row_line, std::mem::take(&mut row)));
+                }
+                row.clear();
+                line += 1;
+                row_line = line;
+            }
+            c if c == delimiter && !quoted => row.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(BuldakError::Config(format!("line {}: quote is never closed", row_line)));
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.trim().is_empty()) {
+        rows.push((row_line, row));
+    }
+    Ok(rows)
+}
+
+fn resolve_mapping(header: Option<&[String]>, options: &ImportOptions, categories: &[Category]) -> Result<Mapping> {
+    let find_column = |column: &str| -> Result<usize> {
+        if let Ok(number) = column.parse::<usize>() && number > 0 {
+            return Ok(number - 1);
+        }
+        header
+            .and_then(|names| names.iter().position(|name| name.trim().to_lowercase() == column.trim().to_lowercase()))
+            .ok_or_else(|| BuldakError::Config(format!("no column '{}' in the file", column)))
+    };
+    let named = |names: &[&str]| -> Option<usize> {
+        header?.iter().position(|name| names.contains(&name.trim().to_lowercase().as_str()))
+    };
+    let explicit = |target: &str| options.mapping.iter().find(|(t, _)| t == target).map(|(_, column)| column.as_str());
+
+    for (target, _) in &options.mapping {
+        if !["date", "comment", "currency"].contains(&target.as_str()) && !categories.iter().any(|c| c.key == *target) {
+            return Err(BuldakError::UnknownCategory(target.clone()));
+        }
+    }
+    let date = match explicit("date") {
+        Some(column) => find_column(column)?,
+        None => named(&["date", "ημερομηνία"]).ok_or_else(|| BuldakError::Config("no date column, map one with --map date=<column>".to_string()))?,
+    };
+    let comment = match explicit("comment") {
+        Some(column) => Some(find_column(column)?),
+        None => named(&["comment", "comments", "σχόλια"]),
+    };
+    let currency = match explicit("currency") {
+        Some(column) => Some(find_column(column)?),
+        None => named(&["currency", "νόμισμα"]),
+    };
+    let mut amounts = Vec::new();
+    for category in categories.iter().filter(|c| !c.archived) {
+        let column = match explicit(&category.key) {
+            Some(column) => Some(find_column(column)?),
+            None => named(&[category.key.as_str(), category.name.to_lowercase().as_str()]),
+        };
+        if let Some(column) = column {
+            amounts.push((category.id, column));
+        }
+    }
+    if amounts.is_empty() {
+        return Err(BuldakError::Config("no amount column, map one with --map <category key>=<column>".to_string()));
+    }
+    Ok(Mapping { date, comment, currency, amounts })
+}
+
+/// Reads the file into records without writing anything, flagging the duplicates.
+pub fn plan_import(repo: &dyn Repository, text: &str, options: &ImportOptions) -> Result<Vec<ImportRow>> {
+    let mut rows = parse_csv(text, options.delimiter)?;
+    let categories = repo.categories()?;
+    let header = if options.header && !rows.is_empty() { Some(rows.remove(0).1) } else { None };
+    let mapping = resolve_mapping(header.as_deref(), options, &categories)?;
+    let currency = match &options.currency {
+        Some(currency) => currency.clone(),
+        None => repo.base_currency()?,
+    };
+
+    let mut planned: Vec<ImportRow> = rows.iter()
+        .map(|(line, fields)| ImportRow { line: *line, record: read_row(fields, &mapping, options, &currency), duplicate: false })
+        .collect();
+
+    let dates = planned.iter().filter_map(|row| row.record.as_ref().ok().map(|r| r.date));
+    let (Some(from), Some(to)) = (dates.clone().min(), dates.max()) else { return Ok(planned) };
+    let mut seen = repo.records_between(from, to, &RecordFilter::default())?.records;
+    for row in planned.iter_mut() {
+        if let Ok(record) = &row.record {
+            row.duplicate = seen.iter().any(|existing| same_expense(existing, record));
+            seen.push(record.clone());
+        }
+    }
+    Ok(planned)
+}
+
+fn read_row(fields: &[String], mapping: &Mapping, options: &ImportOptions, currency: &str) -> Result<Record> {
+    let field = |column: usize| fields.get(column).map_or("", |f| f.trim());
+    let raw_date = field(mapping.date);
+    let date = NaiveDate::parse_from_str(raw_date, &options.date_format)
+        .map_err(|_| BuldakError::InvalidDate(raw_date.to_string()))?;
+    // the locale with this decimal separator reads the other one as a thousands separator
+    let locale = match options.decimal {
+        ',' => Locale::Greek,
+        '.' => Locale::English,
+        _ => Locale::Plain,
+    };
+    // each column is read on its own, a negative amount is a refund and not a split
+    let amounts = mapping.amounts.iter()
+        .map(|(category_id, column)| {
+            let amount = match locale {
+                Locale::Plain => convert_to_money(&field(*column).replace(options.decimal, "."), locale)?,
+                _ => convert_to_money(field(*column), locale)?,
+            };
+            Ok((*category_id, amount))
+        })
+        .collect::<Result<_>>()?;
+    let currency = mapping.currency.map(field).filter(|c| !c.is_empty()).unwrap_or(currency);
+    let record = Record {
+        id: 0,
+        amounts,
+        comments: mapping.comment.map_or("", field).to_string(),
+        date,
+        currency: normalize_currency(currency)?,
+        rate: None,
+    };
+    if record.amounts.values().all(|amount| amount.is_zero()) {
+        return Err(BuldakError::InvalidAmount { input: fields.join(" "), reason: "no amount".to_string() });
+    }
+    Ok(record)
+}
+
+/// Comments are left out on purpose: the same purchase is often described differently.
+fn same_expense(a: &Record, b: &Record) -> bool {
+    let lines = |r: &Record| {
+        let mut lines: Vec<_> = r.amounts.iter().filter(|(_, amount)| !amount.is_zero()).map(|(id, amount)| (*id, *amount)).collect();
+        lines.sort();
+        lines
+    };
+    a.date == b.date && a.currency == b.currency && lines(a) == lines(b)
+}
+
+/// Saves the valid rows in one transaction, duplicates only when asked. Nothing is saved
+/// when any row is invalid.
+pub fn commit_import(repo: &dyn Repository, rows: &[ImportRow], keep_duplicates: bool) -> Result<usize> {
+    if let Some(row) = rows.iter().find(|row| row.record.is_err()) {
+        return Err(BuldakError::Config(format!("line {} can't be imported, nothing was saved", row.line)));
+    }
+    let records: Vec<Record> = rows.iter()
+        .filter(|row| keep_duplicates || !row.duplicate)
+        .filter_map(|row| row.record.as_ref().ok().cloned())
+        .collect();
+    repo.save_records(&records)?;
+    Ok(records.len())
+}
+
+/// Dry-run table: line, status, date, amounts per category key, comment.
+pub fn preview_lines(rows: &[ImportRow], categories: &[Category]) -> Vec<String> {
+    rows.iter().map(|row| match &row.record {
+        Ok(record) => {
+            let amounts = categories.iter()
+                .filter_map(|c| record.amounts.get(&c.id).filter(|a| !a.is_zero()).map(|amount| format!("{}={}", c.key, amount)))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let status = if row.duplicate { "dup" } else { "ok" };
+            format!("{:>5}  {:<6} {}  {:<30} {} {}", row.line, status, record.date.format("%Y-%m-%d"), amounts, record.currency, record.comments)
+        }
+        Err(err) => format!("{:>5}  {:<6} {}", row.line, "error", err),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_validator::into_record;
+    use crate::period::Period;
+    use crate::repository::memory::InMemoryRepository;
+
+    fn d(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_csv_quotes() {
+        let rows = parse_csv("a;\"b;\"\"c\"\"\"\r\n\n\"multi\nline\";2\n", ';').unwrap();
+        assert_eq!(rows, vec![
+            (1, vec!["a".to_string(), "b;\"c\"".to_string()]),
+            (3, vec!["multi\nline".to_string(), "2".to_string()]),
+        ]);
+        assert!(parse_csv("\"open", ',').is_err());
+    }
+
+    #[test]
+    fn test_plan_with_mapping_and_duplicates() {
+        let repo = InMemoryRepository::new();
+        let existing = into_record(&[(1, "10")], "old", "2025-03-02", "EUR", Locale::Plain).unwrap();
+        repo.save_record(&existing).unwrap();
+        let text = "Ημέρα;Super;Notes;Μπύρα\n02/03/2025;10,00;lidl;\n03/03/2025;5+2,5;;1\n04/03/2025;abc;;\n03/03/2025;7,50;again;1\n";
+        let options = ImportOptions {
+            delimiter: ';',
+            decimal: ',',
+            date_format: "%d/%m/%Y".to_string(),
+            mapping: vec![("date".to_string(), "1".to_string()), ("store".to_string(), "super".to_string()), ("comment".to_string(), "Notes".to_string())],
+            ..Default::default()
+        };
+
+        let rows = plan_import(&repo, text, &options).unwrap();
+        assert_eq!(rows.iter().map(|r| (r.line, r.duplicate, r.record.is_ok())).collect::<Vec<_>>(),
+            [(2, true, true), (3, false, true), (4, false, false), (5, true, true)]);
+        let second = rows[1].record.as_ref().unwrap();
+        assert_eq!((second.amount(1), second.amount(2)), ("7.50".parse().unwrap(), "1".parse().unwrap()), "beer mapped by its name");
+
+        assert!(commit_import(&repo, &rows, false).is_err());
+        assert_eq!(repo.records_for_period(&Period::Month(d("2025-03-01"))).unwrap().records.len(), 1, "nothing saved");
+
+        let rows: Vec<ImportRow> = rows.into_iter().filter(|r| r.record.is_ok()).collect();
+        assert_eq!(commit_import(&repo, &rows, false).unwrap(), 1);
+        assert_eq!(repo.records_for_period(&Period::Month(d("2025-03-01"))).unwrap().records.len(), 2);
+    }
+
+    #[test]
+    fn test_thousands_separators() {
+        let repo = InMemoryRepository::new();
+        let greek = ImportOptions { delimiter: ';', decimal: ',', ..Default::default() };
+        let rows = plan_import(&repo, "date;store;beer\n2025-03-02;1.234,56;2,5\n", &greek).unwrap();
+        let record = rows[0].record.as_ref().unwrap();
+        assert_eq!((record.amount(1), record.amount(2)), ("1234.56".parse().unwrap(), "2.5".parse().unwrap()));
+
+        let rows = plan_import(&repo, "date;store\n2025-03-02;\"1,234.56\"\n", &ImportOptions { delimiter: ';', ..Default::default() }).unwrap();
+        assert_eq!(rows[0].record.as_ref().unwrap().amount(1), "1234.56".parse().unwrap());
+        let rows = plan_import(&repo, "date;store\n2025-03-02;1.23,5\n", &greek).unwrap();
+        assert!(rows[0].record.is_err(), "misplaced grouping is rejected");
+    }
+
+    #[test]
+    fn test_negative_amounts_are_not_split() {
+        let repo = InMemoryRepository::new();
+        let rows = plan_import(&repo, "date,store,beer\n2025-03-02,20,-5\n", &ImportOptions::default()).unwrap();
+        let record = rows[0].record.as_ref().unwrap();
+        assert_eq!((record.amount(1), record.amount(2)), ("20".parse().unwrap(), "-5".parse().unwrap()));
+        assert!(preview_lines(&rows, &repo.categories().unwrap())[0].contains("store=20.00 beer=-5.00"));
+    }
+
+    #[test]
+    fn test_export_round_trip_keeps_currency() {
+        let repo = InMemoryRepository::new();
+        let text = "id,date,store,beer,allos,day total,currency,rate,comments\n1,2025-03-02,10.00,0.00,0.00,10.00,USD,0.925,\n";
+        let rows = plan_import(&repo, text, &ImportOptions::default()).unwrap();
+        let record = rows[0].record.as_ref().unwrap();
+        assert_eq!((record.amount(1), record.currency.as_str()), ("10".parse().unwrap(), "USD"));
+    }
+
+    #[test]
+    fn test_export_header_maps_itself() {
+        let repo = InMemoryRepository::new();
+        let text = "id;date;store;beer;allos;day total;comments\n1;2025-03-02;15.00;4.00;0.00;19.00;lidl\n";
+        let rows = plan_import(&repo, text, &ImportOptions { delimiter: ';', ..Default::default() }).unwrap();
+        let record = rows[0].record.as_ref().unwrap();
+        assert_eq!((record.get_day_summary(), record.comments.as_str()), ("19".parse().unwrap(), "lidl"));
+        assert!(plan_import(&repo, "when,store\n2025-03-02,1\n", &ImportOptions::default()).is_err());
+        assert!(plan_import(&repo, "date,wine\n2025-03-02,1\n", &ImportOptions {
+            mapping: vec![("wine".to_string(), "2".to_string())],
+            ..Default::default()
+        }).is_err());
+    }
+}
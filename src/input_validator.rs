@@ -1,144 +1,277 @@
-use crate::db_repo::Record;
-use chrono::NaiveDate;
-
-pub fn validate(input: &str, no_validation : bool ) -> String {
-    if input.is_empty() ||
-        no_validation  ||
-        (input.contains("+") && input.find("+").unwrap() > 0) ||
-        (input.starts_with("-") && input.len() > 1) {
+use crate::currency::normalize_currency;
+use crate::model::{fold_accents, Record};
+use crate::error::{BuldakError, Result};
+use crate::expression::{evaluate, ExpressionError};
+use crate::locale::Locale;
+use crate::money::Money;
+use crate::split::apply_split;
+use chrono::{Datelike, Days, NaiveDate, Utc, Weekday};
+use indexmap::IndexMap;
+
+pub fn validate(input: &str, no_validation : bool, locale: Locale) -> String {
+    if input.is_empty() || no_validation {
         //all ok
         return String::new()
     }
-    if let Err(err) = input.parse::<f64>() {
-        format!("{}", err)
-    } else {
-        String::new()
+    match evaluate_in(input, locale) {
+        Ok(_) => String::new(),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// Greek weekday names without accents and their usual abbreviations, Monday first.
+const GREEK_WEEKDAYS: [(&str, &str); 7] = [
+    ("δευτερα", "δευ"),
+    ("τριτη", "τρι"),
+    ("τεταρτη", "τετ"),
+    ("πεμπτη", "πεμ"),
+    ("παρασκευη", "παρ"),
+    ("σαββατο", "σαβ"),
+    ("κυριακη", "κυρ"),
+];
+
+/// Reads the date field: `2025-03-14`, `today`, `yesterday`, `-3` days ago, a day of the
+/// current month such as `14`, `14/3` of the current year, `14/3/2025`, or a weekday name
+/// (`fri`, `παρασκευή`) meaning the last such day up to today. Greek words work too.
+pub fn resolve_date(input: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let invalid = || BuldakError::InvalidDate(input.to_string());
+    let text = fold_accents(input.trim());
+    if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    let days_ago = match text.as_str() {
+        "today" | "σημερα" => Some(0),
+        "yesterday" | "χθες" => Some(1),
+        "προχθες" => Some(2),
+        _ => text.strip_prefix('-').and_then(|days| days.parse::<u64>().ok()),
+    };
+    if let Some(days) = days_ago {
+        return today.checked_sub_days(Days::new(days)).ok_or_else(invalid);
+    }
+    let weekday = text.parse::<Weekday>().ok().or_else(|| {
+        GREEK_WEEKDAYS.iter()
+            .position(|(name, short)| text == *name || text == *short)
+            .and_then(|i| Weekday::try_from(i as u8).ok())
+    });
+    if let Some(weekday) = weekday {
+        let back = (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+        return Ok(today - Days::new(back as u64));
     }
+    let parts: Vec<&str> = text.split(['/', '.']).collect();
+    let number = |part: &str| part.parse::<u32>().map_err(|_| invalid());
+    let (day, month, year) = match parts[..] {
+        [day] => (number(day)?, today.month(), today.year()),
+        [day, month] => (number(day)?, number(month)?, today.year()),
+        [day, month, year] if year.len() == 2 => (number(day)?, number(month)?, 2000 + number(year)? as i32),
+        [day, month, year] if year.len() == 4 => (number(day)?, number(month)?, number(year)? as i32),
+        _ => return Err(invalid()),
+    };
+    NaiveDate::from_ymd_opt(year, month, day).ok_or_else(invalid)
 }
 
+/// Builds a record from raw `(category id, expression)` pairs in display order.
 pub fn into_record(
-    store_price: &str,
-    beer_price: &str,
-    allos_price: &str,
+    amounts: &[(i64, &str)],
     comments: &str,
     date: &str,
-) -> Record {
-    let mut store = convert_to_f32(store_price);
-    let mut beer = convert_to_f32(beer_price);
-    let allos = convert_to_f32(allos_price);
-    //Processing shortcut of -00.00 beer from store proce
-    if beer < 0.0 {
-        store += beer;
-        beer = beer.abs();
-    }
-    store = format!("{:.2}", store).parse::<f32>().unwrap();
-
-    let naive_date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
-    Record {
+    currency: &str,
+    locale: Locale,
+) -> Result<Record> {
+    let parsed: IndexMap<i64, Money> = amounts
+        .iter()
+        .map(|(category_id, price)| Ok((*category_id, convert_to_money(price, locale)?)))
+        .collect::<Result<IndexMap<i64, Money>>>()?;
+    // a receipt typed in one category with negative amounts taken out of it for others
+    let parsed = apply_split(parsed).map_err(|err| BuldakError::InvalidAmount { input: amounts_text(amounts), reason: err.to_string() })?;
+
+    let naive_date = resolve_date(date, Utc::now().date_naive())?;
+    Ok(Record {
         id: 0,
-        store,
-        beer,
-        allos,
+        amounts: parsed,
         comments: comments.to_string(),
         date: naive_date,
-    }
+        currency: normalize_currency(currency)?,
+        rate: None,
+    })
 }
 
-fn convert_to_f32(str : &str) -> f32{
-    if str.is_empty() {
-        return 0.0
-    }
+/// The typed amounts as one line for an error message, e.g. `54.20, -12, -5`.
+fn amounts_text(amounts: &[(i64, &str)]) -> String {
+    amounts.iter().map(|(_, price)| price.trim()).filter(|price| !price.is_empty()).collect::<Vec<_>>().join(", ")
+}
 
-    let (working_str, multiplier) = if str.starts_with('-') {
-        (&str[1..], -1.0)
-    } else {
-        (str, 1.0)
-    };
+/// [`evaluate`] for an amount typed with the separators of `locale`.
+fn evaluate_in(input: &str, locale: Locale) -> std::result::Result<Money, ExpressionError> {
+    evaluate(&normalize_numbers(input, locale)?)
+}
 
-    let result = match working_str.find('+') {
-        Some(_) => {
-            let sanitized = working_str.replace(['(', ')'], "");
-
-            let parse_term = |raw: &str| {
-                raw.trim()
-                    .parse::<f32>()
-                    .expect("expected a numeric term in '+'-separated expression")
-            };
-
-            sanitized
-                .split('+')
-                .filter(|term| !term.trim().is_empty())
-                .map(parse_term)
-                .sum::<f32>()
+/// Rewrites every number of an expression such as `1.234,56+2` into the `1234.56` that
+/// [`evaluate`] reads, see [`Locale::normalize`]. Numbers are padded with spaces to the
+/// width they were typed with, so that error positions still point into the typed text.
+fn normalize_numbers(input: &str, locale: Locale) -> std::result::Result<String, ExpressionError> {
+    let is_number = |c: char| c.is_ascii_digit() || c == '.' || c == ',';
+    let chars: Vec<char> = input.chars().collect();
+    let mut normalized = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !is_number(chars[i]) {
+            normalized.push(chars[i]);
+            i += 1;
+            continue;
         }
-        None => {
-            if working_str.is_empty() { 0.0 } else { working_str.parse::<f32>().unwrap() }
+        let start = i;
+        while i < chars.len() && is_number(chars[i]) {
+            i += 1;
         }
-    };
+        let text: String = chars[start..i].iter().collect();
+        if !text.contains(['.', ',']) {
+            normalized.push_str(&text);
+            continue;
+        }
+        let number = locale.normalize(&text).ok_or(ExpressionError::InvalidNumber { position: start + 1, text })?;
+        normalized.push_str(&format!("{:<width$}", number, width = i - start));
+    }
+    Ok(normalized)
+}
 
-    let final_value = result * multiplier;
-    format!("{:.2}", final_value).parse::<f32>().unwrap()
+/// Evaluates an amount field with [`evaluate`]; an empty field is zero.
+pub fn convert_to_money(str : &str, locale: Locale) -> Result<Money> {
+    if str.trim().is_empty() {
+        return Ok(Money::ZERO)
+    }
+    evaluate_in(str, locale).map_err(|err| BuldakError::InvalidAmount { input: str.to_string(), reason: err.to_string() })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const STORE: i64 = 1;
+    const BEER: i64 = 2;
+    const ALLOS: i64 = 3;
+
+    fn m(amount: &str) -> Money {
+        amount.parse().unwrap()
+    }
+
     #[test]
-    fn test_convert_to_f32() {
-        assert_eq!(convert_to_f32("10.5"), 10.5);
-        assert_eq!(convert_to_f32("0"), 0.0);
-        assert_eq!(convert_to_f32(""), 0.0);
+    fn test_convert_to_money() {
+        assert_eq!(convert_to_money("10.5", Locale::Plain).unwrap(), m("10.5"));
+        assert_eq!(convert_to_money("0", Locale::Plain).unwrap(), m("0.0"));
+        assert_eq!(convert_to_money("", Locale::Plain).unwrap(), m("0.0"));
+
+        assert_eq!(convert_to_money("10+5", Locale::Plain).unwrap(), m("15.0"));
+        assert_eq!(convert_to_money("10.50+4.50", Locale::Plain).unwrap(), m("15.0"));
+        assert_eq!(convert_to_money("1+1+1", Locale::Plain).unwrap(), m("3.0"));
 
-        assert_eq!(convert_to_f32("10+5"), 15.0);
-        assert_eq!(convert_to_f32("10.50+4.50"), 15.0);
-        assert_eq!(convert_to_f32("1+1+1"), 3.0);
+        assert_eq!(convert_to_money("1.111+1.111", Locale::Plain).unwrap(), m("2.22"));
 
-        assert_eq!(convert_to_f32("1.111+1.111"), 2.22);
+        assert_eq!(convert_to_money("+10", Locale::Plain).unwrap(), m("10.0"));
+        assert_eq!(convert_to_money(" ", Locale::Plain).unwrap(), m("0.0"));
 
-        assert_eq!(convert_to_f32("10+"), 10.0);
-        assert_eq!(convert_to_f32("+10"), 10.0);
+        assert_eq!(convert_to_money("-5+10", Locale::Plain).unwrap(), m("5.0"));
+        assert_eq!(convert_to_money("-(5+10)", Locale::Plain).unwrap(), m("-15.0"));
+        assert_eq!(convert_to_money("-1.5", Locale::Plain).unwrap(), m("-1.5"));
+        assert_eq!(convert_to_money("40-10%", Locale::Plain).unwrap(), m("36.0"));
+        assert!(convert_to_money("10+", Locale::Plain).is_err());
+        assert!(convert_to_money("-", Locale::Plain).is_err());
+    }
 
-        assert_eq!(convert_to_f32("-5+10"), -15.0);
-        assert_eq!(convert_to_f32("-(5+10)"), -15.0);
-        assert_eq!(convert_to_f32("-1.5"), -1.5);
-        assert_eq!(convert_to_f32("-"), 0.0);
+    #[test]
+    fn test_numbers_in_locale() {
+        assert_eq!(convert_to_money("1,56+1", Locale::Plain).unwrap(), m("2.56"));
+        assert_eq!(convert_to_money("1.500+2,5", Locale::Greek).unwrap(), m("1502.5"));
+        assert_eq!(convert_to_money("1.234,56*2", Locale::Greek).unwrap(), m("2469.12"));
+        assert_eq!(convert_to_money("(1.234,56)%", Locale::Greek).unwrap(), m("12.35"));
+        assert_eq!(convert_to_money("1,500/3", Locale::English).unwrap(), m("500"));
+        assert_eq!(validate("2+1.2.3", false, Locale::Greek), "'1.2.3' at 3 is not a number");
+        assert_eq!(validate("1.234,56+abc", false, Locale::Greek), "unexpected 'a' at 10");
+        assert!(convert_to_money("0.500", Locale::Greek).is_err(), "neither 500 nor clearly 0.5");
     }
 
     #[test]
     fn test_into_record_basic() {
-        let record = into_record("10.50", "5.00", "1.00", "test comment", "2023-12-21");
-        assert_eq!(record.store, 10.50);
-        assert_eq!(record.beer, 5.00);
-        assert_eq!(record.allos, 1.00);
+        let record = into_record(&[(STORE, "10.50"), (BEER, "5.00"), (ALLOS, "1.00")], "test comment", "2023-12-21", "EUR", Locale::Plain).unwrap();
+        assert_eq!(record.amount(STORE), m("10.50"));
+        assert_eq!(record.amount(BEER), m("5.00"));
+        assert_eq!(record.amount(ALLOS), m("1.00"));
         assert_eq!(record.comments, "test comment");
         assert_eq!(record.date.to_string(), "2023-12-21");
     }
 
     #[test]
     fn test_into_record_beer_shortcut() {
-        let record = into_record("20.00", "-5.00", "0.0", "", "2023-12-21");
-        assert_eq!(record.store, 15.00);
-        assert_eq!(record.beer, 5.00);
+        let record = into_record(&[(STORE, "20.00"), (BEER, "-5.00"), (ALLOS, "0.0")], "", "2023-12-21", "EUR", Locale::Plain).unwrap();
+        assert_eq!(record.amount(STORE), m("15.00"));
+        assert_eq!(record.amount(BEER), m("5.00"));
+    }
+
+    #[test]
+    fn test_into_record_split() {
+        let record = into_record(&[(STORE, "54.20"), (BEER, "-12"), (ALLOS, "-5")], "", "2023-12-21", "EUR", Locale::Plain).unwrap();
+        assert_eq!((record.amount(STORE), record.amount(BEER), record.amount(ALLOS)), (m("37.20"), m("12"), m("5")));
+        let record = into_record(&[(STORE, ""), (BEER, "10"), (ALLOS, "-2.5")], "", "2023-12-21", "EUR", Locale::Plain).unwrap();
+        assert_eq!((record.amount(BEER), record.amount(ALLOS)), (m("7.50"), m("2.50")));
+
+        let result = into_record(&[(STORE, "10"), (BEER, "-8"), (ALLOS, "-4")], "", "2023-12-21", "EUR", Locale::Plain);
+        assert!(matches!(result, Err(BuldakError::InvalidAmount { input, .. }) if input == "10, -8, -4"));
     }
 
     #[test]
     fn test_into_record_with_summation() {
-        let record = into_record("10+5.5", "2+2", "0", "calc", "2023-12-21");
-        assert_eq!(record.store, 15.50);
-        assert_eq!(record.beer, 4.00);
+        let record = into_record(&[(STORE, "10+5.5"), (BEER, "2+2"), (ALLOS, "0")], "calc", "2023-12-21", "EUR", Locale::Plain).unwrap();
+        assert_eq!(record.amount(STORE), m("15.50"));
+        assert_eq!(record.amount(BEER), m("4.00"));
     }
 
     #[test]
     fn test_into_record_with_negative_summation() {
-        let record = into_record("10+5", "-2+2+1+1", "0", "calc", "2023-12-21");
-        assert_eq!(record.store, 9.00);
-        assert_eq!(record.beer, 6.00);
+        let record = into_record(&[(STORE, "10+5"), (BEER, "-(2+2+1+1)"), (ALLOS, "0")], "calc", "2023-12-21", "EUR", Locale::Plain).unwrap();
+        assert_eq!(record.amount(STORE), m("9.00"));
+        assert_eq!(record.amount(BEER), m("6.00"));
     }
 
     #[test]
-    #[should_panic]
     fn test_into_record_invalid_date() {
-        into_record("10", "5", "0", "", "invalid-date");
+        let result = into_record(&[(STORE, "10"), (BEER, "5"), (ALLOS, "0")], "", "invalid-date", "EUR", Locale::Plain);
+        assert!(matches!(result, Err(BuldakError::InvalidDate(_))));
+    }
+
+    #[test]
+    fn test_resolve_date() {
+        // a Friday
+        let today = NaiveDate::from_ymd_opt(2025, 3, 14).unwrap();
+        let d = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let resolve = |input: &str| resolve_date(input, today).ok();
+        assert_eq!(resolve("2024-02-29"), Some(d("2024-02-29")));
+        assert_eq!(resolve(" Today "), Some(today));
+        assert_eq!(resolve("yesterday"), Some(d("2025-03-13")));
+        assert_eq!(resolve("Χθές"), Some(d("2025-03-13")));
+        assert_eq!(resolve("-3"), Some(d("2025-03-11")));
+        assert_eq!(resolve("-0"), Some(today));
+        assert_eq!(resolve("3"), Some(d("2025-03-03")));
+        assert_eq!(resolve("28/2"), Some(d("2025-02-28")));
+        assert_eq!(resolve("1.12.24"), Some(d("2024-12-01")));
+        assert_eq!(resolve("5/1/2023"), Some(d("2023-01-05")));
+        assert_eq!(resolve("fri"), Some(today));
+        assert_eq!(resolve("Monday"), Some(d("2025-03-10")));
+        assert_eq!(resolve("σάββατο"), Some(d("2025-03-08")));
+        assert_eq!(resolve("τρι"), Some(d("2025-03-11")));
+
+        for bad in ["", "32", "31/4", "30/2", "2025-02-30", "tomorrow", "14/3/202", "-x", "1/2/3/4"] {
+            assert!(matches!(resolve_date(bad, today), Err(BuldakError::InvalidDate(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_invalid_terms_are_errors_not_panics() {
+        assert!(matches!(convert_to_money("12+abc", Locale::Plain), Err(BuldakError::InvalidAmount { .. })));
+        assert!(convert_to_money("abc", Locale::Plain).is_err());
+        assert!(into_record(&[(STORE, "12+abc")], "", "2023-12-21", "EUR", Locale::Plain).is_err());
+        assert!(into_record(&[(STORE, "12")], "", "2023-12-21", "euro", Locale::Plain).is_err());
+        assert_eq!(into_record(&[(STORE, "12")], "", "2023-12-21", "usd", Locale::Plain).unwrap().currency, "USD");
+        assert!(!validate("12+abc", false, Locale::Plain).is_empty());
+        assert!(validate("12+3", false, Locale::Plain).is_empty());
+        assert_eq!(validate("2*(3+4", false, Locale::Plain), "'(' at 3 is never closed");
     }
 }
@@ -1,8 +1,11 @@
-use crate::db_repo::{save_record};
-use chrono::{Utc};
+use crate::model::{Category, Record};
+use crate::money::Money;
+use indexmap::IndexMap;
+use crate::repository::Repository;
+use chrono::{NaiveDate, Utc};
 use crossterm::event::{KeyEvent};
-use ratatui::layout::Rect;
-use ratatui::widgets::{Borders};
+use ratatui::layout::{Flex, Rect};
+use ratatui::widgets::{Borders, Clear};
 use ratatui::{
     Frame,
     layout::{Constraint, Layout},
@@ -11,7 +14,14 @@ use ratatui::{
     widgets::{Block, Paragraph},
 };
 use tui_textarea::{TextArea};
-use crate::input_validator::{into_record, validate};
+use crate::calendar::CalendarState;
+use crate::input_validator::{convert_to_money, into_record, resolve_date, validate};
+use crate::locale::Locale;
+use crate::error::Result;
+use crate::period::Period;
+use crate::split::{Split, SplitError};
+use crate::popup::popup_area;
+use crate::undo::UndoAction;
 
 /// App holds the state of the application
 pub struct InputsState<'a> {
@@ -19,13 +29,44 @@ pub struct InputsState<'a> {
     pub input_mode: InputMode,
     pub inputs: Vec<TextAreaHolder<'a>>,
     pub date_input: TextAreaHolder<'a>,
+    /// Currency of the next record, kept between records like the date.
+    pub currency_input: TextAreaHolder<'a>,
     pub selected_input_index: usize,
+    /// Active categories, one amount input each, followed by the comments input.
+    pub categories: Vec<Category>,
+    /// Set while the inputs hold an existing record loaded from the table.
+    pub editing: Option<EditingRecord>,
+    /// `from..to` typed after pressing `r`.
+    pub range_input: TextAreaHolder<'a>,
+    /// Tag typed after pressing `t`, empty to show every record.
+    pub tag_input: TextAreaHolder<'a>,
+    /// Spending per tag in the tab on screen, listed under the tag prompt.
+    pub tag_totals: IndexMap<String, Money>,
+    /// Separators amounts are typed with.
+    pub locale: Locale,
+    /// Month grid to pick the date from, opened with `d`.
+    pub calendar: CalendarState,
+}
+
+pub struct EditingRecord {
+    pub original: Record,
+    /// Date field content before the record was loaded, restored afterwards.
+    pub previous_date: String,
+    pub previous_currency: String,
 }
 
 pub enum InputMode {
     Normal,
     Editing,
     DateEditing,
+    CurrencyEditing,
+    Categories,
+    ConfirmDelete,
+    RangeEditing,
+    TagFilter,
+    Search,
+    Recurring,
+    Calendar,
 }
 
 pub struct TextAreaHolder<'a> {
@@ -55,23 +96,24 @@ impl<'a> TextAreaHolder<'a> {
     }
 
     pub fn new_validation_disabled_with_value(title: &str, value : &str) -> Self {
-        let holder = TextAreaHolder {
+        
+        TextAreaHolder {
             text_area: TextArea::new(vec!(value.to_string())),
             title: String::from(title),
             error_message: "".to_string(),
             no_validation: true
-        };
-        holder
+        }
     }
 
+    /// The field name, followed by what is wrong with the input when it was rejected.
     pub fn get_title(&self) -> String {
         if self.error_message.is_empty() {
             return self.title.clone();
         }
-        self.error_message.clone()
+        format!("{}: {}", self.title, self.error_message)
     }
 
-    pub fn get_block<'b>(&self) -> Block<'_> {
+    pub fn get_block(&self) -> Block<'_> {
         if self.error_message.is_empty() {
             Block::default()
                 .borders(Borders::ALL)
@@ -82,37 +124,78 @@ impl<'a> TextAreaHolder<'a> {
                 .borders(Borders::ALL)
                 .border_style(Color::LightRed)
                 .title_style(Color::Red)
-                .title(self.title.as_str()).clone()
+                .title(self.get_title())
         }
     }
 
 }
 
 impl InputsState<'_> {
-    pub fn new() -> Self {
-        Self {
+    pub fn new(categories: Vec<Category>, currency: &str, locale: Locale) -> Self {
+        let mut state = Self {
             input_mode: InputMode::Normal,
-            inputs: vec![
-                TextAreaHolder::new("Προϊόντα"),
-                TextAreaHolder::new("Μπύρα"),
-                TextAreaHolder::new("Αλλος"),
-                TextAreaHolder::new_validation_disabled("Σχόλια")
-            ],
+            inputs: vec![],
             date_input: TextAreaHolder::new_validation_disabled_with_value("Ημερομηνία", Utc::now().date_naive().to_string().as_str()),
+            currency_input: TextAreaHolder::new_validation_disabled_with_value("Νόμισμα", currency),
             selected_input_index: 0,
-        }
+            categories: categories.into_iter().filter(|c| !c.archived).collect(),
+            editing: None,
+            range_input: TextAreaHolder::new_validation_disabled("Διάστημα (ΕΕΕΕ-ΜΜ-ΗΗ..ΕΕΕΕ-ΜΜ-ΗΗ)"),
+            tag_input: TextAreaHolder::new_validation_disabled("Ετικέτα (κενό για όλες τις εγγραφές)"),
+            tag_totals: IndexMap::new(),
+            locale,
+            calendar: CalendarState::new(),
+        };
+        state.inputs_to_default();
+        state
     }
 
     pub fn inputs_to_default(&mut self){
-        self.inputs = vec!(TextAreaHolder::new("Προϊόντα"),
-                           TextAreaHolder::new("Μπύρα"),
-                           TextAreaHolder::new("Αλλος"),
-                           TextAreaHolder::new_validation_disabled("Σχόλια"));
+        self.inputs = self.categories.iter().map(|c| TextAreaHolder::new(&c.name)).collect();
+        self.inputs.push(TextAreaHolder::new_validation_disabled("Σχόλια"));
+    }
+
+    /// Rebuilds the amount inputs after categories were added, renamed, reordered or archived.
+    pub fn set_categories(&mut self, categories: Vec<Category>) {
+        self.categories = categories.into_iter().filter(|c| !c.archived).collect();
+        self.selected_input_index = 0;
+        self.inputs_to_default();
+    }
+
+    /// Fills the inputs with an existing record so that the next submit updates it.
+    pub fn load_record(&mut self, record: &Record) {
+        let (previous_date, previous_currency) = match self.editing.take() {
+            Some(editing) => (editing.previous_date, editing.previous_currency),
+            None => (self.date_input.text_area.lines()[0].clone(), self.currency_input.text_area.lines()[0].clone()),
+        };
+        self.inputs_to_default();
+        for (holder, category) in self.inputs.iter_mut().zip(self.categories.iter()) {
+            if let Some(amount) = record.amounts.get(&category.id) {
+                holder.text_area = TextArea::new(vec!(self.locale.number(*amount)));
+            }
+        }
+        if let Some(comments) = self.inputs.last_mut() {
+            comments.text_area = TextArea::new(vec!(record.comments.clone()));
+        }
+        self.date_input.text_area = TextArea::new(vec!(record.date.format("%Y-%m-%d").to_string()));
+        self.currency_input.text_area = TextArea::new(vec!(record.currency.clone()));
+        self.editing = Some(EditingRecord { original: record.clone(), previous_date, previous_currency });
+        self.selected_input_index = 0;
+        self.input_mode = InputMode::Editing;
+    }
+
+    /// Leaves edit mode without saving and restores the date the user had before.
+    pub fn cancel_editing(&mut self) {
+        if let Some(editing) = self.editing.take() {
+            self.date_input.text_area = TextArea::new(vec!(editing.previous_date));
+            self.currency_input.text_area = TextArea::new(vec!(editing.previous_currency));
+            self.inputs_to_default();
+        }
     }
 
     pub fn move_cursor_to_next_input(&mut self) {
         let text_area = self.inputs.get_mut(self.selected_input_index).unwrap();
-        text_area.error_message = validate(text_area.text_area.lines()[0].as_str(), text_area.no_validation);
+        text_area.error_message = validate(text_area.text_area.lines()[0].as_str(), text_area.no_validation, self.locale);
         self.selected_input_index += 1;
         if self.selected_input_index >= self.inputs.len() {
             self.selected_input_index = 0;
@@ -122,23 +205,212 @@ impl InputsState<'_> {
     pub fn input(&mut self, key: KeyEvent) {
         let text_area = self.inputs.get_mut(self.selected_input_index).unwrap();
         text_area.text_area.input(key);
+        // once a field was rejected, keep telling what is wrong until it is fixed
+        if !text_area.error_message.is_empty() {
+            text_area.error_message = validate(text_area.text_area.lines()[0].as_str(), text_area.no_validation, self.locale);
+        }
+    }
+
+    /// Opens the calendar on the date in the field, or today while the field is not a date.
+    pub fn open_calendar(&mut self, repo: &dyn Repository) -> Result<()> {
+        let date = self.resolved_date().unwrap_or_else(|_| Utc::now().date_naive());
+        self.calendar.open(date, repo)?;
+        self.input_mode = InputMode::Calendar;
+        Ok(())
+    }
+
+    /// Writes the day picked in the calendar into the date field.
+    pub fn set_date(&mut self, date: NaiveDate) {
+        self.date_input.text_area = TextArea::new(vec!(date.format("%Y-%m-%d").to_string()));
+    }
+
+    /// Selects the date so that typing `yesterday` or `14` replaces it.
+    pub fn start_date_editing(&mut self) {
+        self.date_input.text_area.select_all();
+        self.input_mode = InputMode::DateEditing;
     }
 
     pub fn date_input(&mut self, key: KeyEvent) {
         self.date_input.text_area.input(key);
     }
 
-    pub fn submit_message(&mut self) {
+    /// The date field resolved against today, see [`resolve_date`].
+    pub fn resolved_date(&self) -> Result<NaiveDate> {
+        resolve_date(&self.date_input.text_area.lines()[0], Utc::now().date_naive())
+    }
+
+    /// Replaces `yesterday` or `14/3` with the date it stands for, so it keeps meaning
+    /// the same day. Fails, leaving the field as typed, while it is not a date.
+    pub fn commit_date(&mut self) -> Result<()> {
+        self.set_date(self.resolved_date()?);
+        Ok(())
+    }
+
+    /// Title of the date field: its name, the day a shorthand resolves to instead, kept
+    /// short to fit the field, or a warning while it is not a date.
+    fn date_title(&self, name: String) -> (String, bool) {
+        let typed = self.date_input.text_area.lines()[0].trim();
+        match self.resolved_date() {
+            Ok(date) if date.format("%Y-%m-%d").to_string() == typed => (name, true),
+            Ok(date) => (format!("→ {}", date.format("%a %Y-%m-%d")), true),
+            Err(_) => ("Άκυρη ημερομηνία".to_string(), false),
+        }
+    }
+
+    /// How the typed amounts divide a receipt, shown while typing them. `None` while there
+    /// is no split or a field is not an amount yet.
+    pub fn split_preview(&self) -> Option<std::result::Result<Split, SplitError>> {
+        let amounts = self.categories.iter()
+            .zip(self.inputs.iter())
+            .map(|(c, input)| Some((c.id, convert_to_money(&input.text_area.lines()[0], self.locale).ok()?)))
+            .collect::<Option<IndexMap<i64, Money>>>()?;
+        Split::find(&amounts).transpose()
+    }
+
+    /// Help line replaced by the split preview, e.g. `Απόδειξη 54,20 €: Προϊόντα 37,20 € · Μπύρα 12,00 €`.
+    fn split_message(&self) -> Option<Vec<Span<'static>>> {
+        let currency = self.currency_input.text_area.lines()[0].trim().to_uppercase();
+        let name = |id: i64| self.categories.iter().find(|c| c.id == id).map_or(String::new(), |c| c.name.clone());
+        let message = match self.split_preview()? {
+            Ok(split) => {
+                let mut spans = vec![
+                    "Απόδειξη ".yellow(),
+                    format!("{}: ", self.locale.money(split.total, &currency)).yellow().bold(),
+                    format!("{} {}", name(split.from), self.locale.money(split.rest, &currency)).yellow(),
+                ];
+                for (category_id, amount) in &split.parts {
+                    spans.push(format!(" · {} {}", name(*category_id), self.locale.money(*amount, &currency)).yellow());
+                }
+                spans
+            }
+            Err(SplitError::Exceeds { total, taken }) => vec![
+                "Ο διαχωρισμός ξεπερνά την απόδειξη: ".light_red().bold(),
+                format!("{} από {}", self.locale.money(taken, &currency), self.locale.money(total, &currency)).light_red(),
+            ],
+            Err(SplitError::TooLarge) => vec!["Ο διαχωρισμός βγάζει πολύ μεγάλο ποσό".light_red().bold()],
+        };
+        Some(message)
+    }
+
+    pub fn start_currency_editing(&mut self) {
+        self.currency_input.text_area.move_cursor(tui_textarea::CursorMove::End);
+        self.input_mode = InputMode::CurrencyEditing;
+    }
+
+    pub fn currency_input(&mut self, key: KeyEvent) {
+        self.currency_input.text_area.input(key);
+    }
+
+    /// Opens the range prompt filled with the bounds of the tab on screen.
+    pub fn start_range_editing(&mut self, current: &Period) {
+        let (from, to) = current.bounds();
+        self.range_input.text_area = TextArea::new(vec!(format!("{}..{}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"))));
+        self.range_input.text_area.move_cursor(tui_textarea::CursorMove::End);
+        self.input_mode = InputMode::RangeEditing;
+    }
+
+    pub fn range_input(&mut self, key: KeyEvent) {
+        self.range_input.text_area.input(key);
+    }
+
+    pub fn range(&self) -> Result<Period> {
+        Period::parse_range(&self.range_input.text_area.lines()[0])
+    }
+
+    pub fn render_range_popup(&mut self, frame: &mut Frame) {
+        let [area] = Layout::vertical([Constraint::Length(3)])
+            .flex(Flex::Center)
+            .areas(popup_area(frame.area(), 40, 100));
+        self.range_input.text_area.set_cursor_line_style(Style::default());
+        self.range_input.text_area.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Yellow))
+                .title(self.range_input.title.clone()),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(&self.range_input.text_area, area);
+    }
+
+    /// Opens the tag prompt filled with the tag currently filtered on.
+    pub fn start_tag_filter(&mut self, current: Option<&str>, totals: IndexMap<String, Money>) {
+        self.tag_input.text_area = TextArea::new(vec!(current.unwrap_or_default().to_string()));
+        self.tag_input.text_area.move_cursor(tui_textarea::CursorMove::End);
+        self.tag_totals = totals;
+        self.input_mode = InputMode::TagFilter;
+    }
+
+    pub fn tag_input(&mut self, key: KeyEvent) {
+        self.tag_input.text_area.input(key);
+    }
+
+    /// The tag typed, lower case without the `#`; `None` when the prompt was cleared.
+    pub fn tag(&self) -> Option<String> {
+        let tag = self.tag_input.text_area.lines()[0].trim().trim_start_matches('#').to_lowercase();
+        if tag.is_empty() { None } else { Some(tag) }
+    }
+
+    /// Tag prompt with the tags starting with what was typed and their totals underneath.
+    pub fn render_tag_popup(&mut self, frame: &mut Frame, base_currency: &str) {
+        let typed = self.tag().unwrap_or_default();
+        let lines: Vec<Line> = self.tag_totals.iter()
+            .filter(|(tag, _)| tag.starts_with(&typed))
+            .map(|(tag, total)| Line::from(format!("#{:<20} {:>16}", tag, self.locale.money(*total, base_currency))))
+            .collect();
+        let height = lines.len().clamp(1, 15) as u16 + 2;
+        let [input_area, list_area] = Layout::vertical([Constraint::Length(3), Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(popup_area(frame.area(), 40, 100));
+        self.tag_input.text_area.set_cursor_line_style(Style::default());
+        self.tag_input.text_area.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Yellow))
+                .title(self.tag_input.title.clone()),
+        );
+        let list = Paragraph::new(lines)
+            .block(Block::bordered().title("Σύνολα ανά ετικέτα").border_style(Color::Green))
+            .style(Color::Green);
+        frame.render_widget(Clear, input_area);
+        frame.render_widget(&self.tag_input.text_area, input_area);
+        frame.render_widget(Clear, list_area);
+        frame.render_widget(list, list_area);
+    }
+
+    /// Saves the inputs as a new record, or updates the record being edited.
+    /// Returns how to undo an update. On error the inputs are left as typed.
+    pub fn submit_message(&mut self, repo: &dyn Repository) -> Result<Option<UndoAction>> {
         let date = &self.date_input.text_area.lines()[0].clone();
-        let store_price  = &self.inputs.get(0).unwrap().text_area.lines()[0].clone();
-        let beer_price  = &self.inputs.get(1).unwrap().text_area.lines()[0].clone();
-        let allos_price  = &self.inputs.get(2).unwrap().text_area.lines()[0].clone();
-        let comments  = &self.inputs.get(3).unwrap().text_area.lines()[0].clone();
+        let prices = self.inputs.iter().map(|i| i.text_area.lines()[0].clone()).collect::<Vec<String>>();
+        let amounts = self.categories.iter()
+            .zip(prices.iter())
+            .map(|(c, price)| (c.id, price.as_str()))
+            .collect::<Vec<(i64, &str)>>();
+        let comments = prices.last().map(String::as_str).unwrap_or_default();
 
-        let record = into_record(store_price, beer_price, allos_price , comments, date);
+        let currency = self.currency_input.text_area.lines()[0].clone();
+        let mut record = into_record(&amounts, comments, date, &currency, self.locale)?;
 
-        let _ =save_record(&record);
-        self.inputs_to_default()
+        match &self.editing {
+            Some(editing) => {
+                record.id = editing.original.id;
+                // keep lines of archived categories that have no input any more
+                for (category_id, amount) in editing.original.amounts.iter() {
+                    if !self.categories.iter().any(|c| c.id == *category_id) {
+                        record.amounts.insert(*category_id, *amount);
+                    }
+                }
+                repo.update_record(&record)?;
+                let undo = UndoAction::Edited(editing.original.clone());
+                self.cancel_editing();
+                Ok(Some(undo))
+            }
+            None => {
+                repo.save_record(&record)?;
+                self.inputs_to_default();
+                Ok(None)
+            }
+        }
     }
 
     pub fn render(&mut self, frame: &mut Frame, area: Rect) {
@@ -147,17 +419,14 @@ impl InputsState<'_> {
             Constraint::Length(3),
         ]);
         let [help_area, input_area] = vertical.areas(area);
-        let [date, left_input, center_input, right_input,comments_input] = Layout::horizontal([
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
-        ])
-        .areas(input_area);
+        let columns = self.inputs.len() as u32 + 1;
+        let areas = Layout::horizontal((0..columns).map(|_| Constraint::Ratio(1, columns)))
+            .split(input_area);
+        let [date_area, currency_area] = Layout::horizontal([Constraint::Fill(1), Constraint::Length(9)])
+            .areas(areas[0]);
         self.render_help_area(frame, help_area);
-        self.render_input_areas(frame, &[date, left_input, center_input, right_input, comments_input]);
-        self.activate_input(frame, &[left_input, center_input, right_input, comments_input], date);
+        self.render_input_areas(frame, &areas, currency_area);
+        self.activate_input(frame, &areas[1..], date_area, currency_area);
     }
 
     fn render_help_area(&self, frame: &mut Frame, area: Rect) {
@@ -167,8 +436,9 @@ impl InputsState<'_> {
         frame.render_widget(help_message, area);
     }
 
-    fn render_input_areas(&mut self, frame: &mut Frame, areas: &[Rect]) {
+    fn render_input_areas(&mut self, frame: &mut Frame, areas: &[Rect], currency_area: Rect) {
         self.create_date_input(frame, areas[0]);
+        self.create_currency_input(frame, currency_area);
         //frame.render_widget(&date, areas[0]);
         for (i, rect) in areas[1..].iter().enumerate() {
             let text_area_holder = &mut self.inputs.get_mut(i).unwrap();
@@ -186,11 +456,12 @@ impl InputsState<'_> {
         }
     }
 
-    fn activate_input(&mut self, frame: &mut Frame, areas: &[Rect], date_area : Rect) {
+    fn activate_input(&mut self, frame: &mut Frame, areas: &[Rect], date_area : Rect, currency_area: Rect) {
         match self.input_mode {
-            InputMode::Normal => {}
+            InputMode::Normal | InputMode::Categories | InputMode::ConfirmDelete | InputMode::RangeEditing | InputMode::TagFilter | InputMode::Search | InputMode::Recurring | InputMode::Calendar => {}
             #[allow(clippy::cast_possible_truncation)]
             InputMode::Editing => {
+                let active_colour = if self.editing.is_some() { Color::Magenta } else { Color::Yellow };
                 let text_area_holder = &mut self.inputs.get_mut(self.selected_input_index).unwrap();
                 let title = text_area_holder.get_title();
                 let text_area = &mut text_area_holder.text_area;
@@ -200,13 +471,13 @@ impl InputsState<'_> {
                 text_area.set_block(
                     Block::default()
                         .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::Yellow))
+                        .style(Style::default().fg(active_colour))
                         .title(title),
                 );
                 frame.render_widget(&*text_area, areas[self.selected_input_index]);
             }
             InputMode::DateEditing => {
-                let title = self.date_input.get_title();
+                let (title, valid) = self.date_title(self.date_input.title.clone());
                 let text_area = &mut self.date_input.text_area;
                 text_area
                     .set_cursor_line_style(Style::default().add_modifier(Modifier::UNDERLINED));
@@ -214,52 +485,189 @@ impl InputsState<'_> {
                 text_area.set_block(
                     Block::default()
                         .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::Yellow))
+                        .style(Style::default().fg(if valid { Color::Yellow } else { Color::LightRed }))
                         .title(title),
                 );
                 frame.render_widget(&*text_area, date_area);
             }
+            InputMode::CurrencyEditing => {
+                let text_area = &mut self.currency_input.text_area;
+                text_area.set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
+                text_area.set_block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .style(Style::default().fg(Color::Yellow))
+                        .title("Νόμισμα"),
+                );
+                frame.render_widget(&*text_area, currency_area);
+            }
         }
     }
 
+    fn create_currency_input(&mut self, frame: &mut Frame, area: Rect) {
+        let colour = if self.editing.is_some() { Color::Magenta } else { Color::Green };
+        self.currency_input.text_area.set_block(Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(colour))
+            .title("Νόμισμα"));
+        self.currency_input.text_area.set_cursor_style(Style::default());
+        self.currency_input.text_area.set_cursor_line_style(Style::default());
+        frame.render_widget(&self.currency_input.text_area, area);
+    }
+
     fn create_date_input(&mut self, frame: &mut Frame, area: Rect) {
+        let (colour, title) = match &self.editing {
+            Some(editing) => (Color::Magenta, format!("Ημερομηνία (εγγραφή #{})", editing.original.id)),
+            None => (Color::Green, "Ημερομηνία".to_string()),
+        };
+        let (title, valid) = self.date_title(title);
+        let colour = if valid { colour } else { Color::LightRed };
         self.date_input.text_area.set_block(Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::Green))
-            .title("Ημερομηνία").clone());
+            .style(Style::default().fg(colour))
+            .title(title).clone());
         self.date_input.text_area.set_style(Style::default());
         frame.render_widget(&self.date_input.text_area, area);
     }
 
     fn create_help_message(&self) -> (Vec<Span<'_>>, Style) {
+        if matches!(self.input_mode, InputMode::Editing) && let Some(message) = self.split_message() {
+            return (message, Style::default());
+        }
         match self.input_mode {
             InputMode::Normal => (
                 vec![
-                    "Πατήστε ".green().into(),
+                    "Πατήστε ".green(),
                     "e".green().bold(),
                     " για να ξεκινήσετε την επεξεργασία. ".green(),
                     "d".green().bold(),
-                    " για να επεξεργαστείτε την ημερομηνία.".green(),
+                    " για να επεξεργαστείτε την ημερομηνία. ".green(),
+                    "m".green().bold(),
+                    " για το νόμισμα. ".green(),
+                    "c".green().bold(),
+                    " για τις κατηγορίες. ".green(),
+                    "Enter".green().bold(),
+                    " για να διορθώσετε, ".green(),
+                    "x".green().bold(),
+                    " για να διαγράψετε την επιλεγμένη εγγραφή, ".green(),
+                    "u".green().bold(),
+                    " για αναίρεση, ".green(),
+                    "v".green().bold(),
+                    " μήνας/τρίμηνο/έτος, ".green(),
+                    "r".green().bold(),
+                    " για διάστημα, ".green(),
+                    "t".green().bold(),
+                    " για ετικέτα, ".green(),
+                    "/".green().bold(),
+                    " για αναζήτηση, ".green(),
+                    "E".green().bold(),
+                    " για εξαγωγή CSV, ".green(),
+                    "R".green().bold(),
+                    " για τα επαναλαμβανόμενα.".green(),
                 ],
                 Style::default().add_modifier(Modifier::RAPID_BLINK),
             ),
+            InputMode::Editing if self.editing.is_some() => (
+                vec![
+                    "ΕΠΕΞΕΡΓΑΣΙΑ ΕΓΓΡΑΦΗΣ ".magenta().bold(),
+                    format!("#{} ", self.editing.as_ref().map_or(0, |e| e.original.id)).magenta().bold(),
+                    "Πατήστε ".magenta(),
+                    "Esc".magenta().bold(),
+                    " για ακύρωση. ".magenta(),
+                    "Enter".magenta().bold(),
+                    " για να αποθηκεύσετε τις αλλαγές".magenta(),
+                ],
+                Style::default(),
+            ),
             InputMode::Editing => (
                 vec![
-                    "Πατήστε ".green().into(),
+                    "Πατήστε ".green(),
+                    "Esc".green().bold(),
+                    " για να διακόψετε την επεξεργασία. ".green(),
+                    "Enter".green().bold(),
+                    " για να καταγράψετε τα έξοδα".green(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Categories => (
+                vec![
+                    "Πατήστε ".green(),
+                    "Esc".green().bold(),
+                    " για να κλείσετε τις κατηγορίες".green(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Recurring => (
+                vec![
+                    "Πατήστε ".green(),
+                    "Esc".green().bold(),
+                    " για να κλείσετε τα επαναλαμβανόμενα έξοδα".green(),
+                ],
+                Style::default(),
+            ),
+            InputMode::ConfirmDelete => (
+                vec![
+                    "Πατήστε ".red(),
+                    "y".red().bold(),
+                    " για διαγραφή, ".red(),
+                    "n".red().bold(),
+                    " για ακύρωση".red(),
+                ],
+                Style::default(),
+            ),
+            InputMode::RangeEditing => (
+                vec![
+                    "Πατήστε ".green(),
+                    "Esc".green().bold(),
+                    " για ακύρωση, ".green(),
+                    "Enter".green().bold(),
+                    " για να εμφανίσετε το διάστημα".green(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Calendar => (
+                vec![
+                    "Πατήστε ".green(),
+                    "Enter".green().bold(),
+                    " για να επιλέξετε την ημέρα, ".green(),
+                    "Tab".green().bold(),
+                    " για να την πληκτρολογήσετε".green(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Search => (
+                vec![
+                    "Πατήστε ".green(),
+                    "Esc".green().bold(),
+                    " για να κλείσετε την αναζήτηση".green(),
+                ],
+                Style::default(),
+            ),
+            InputMode::TagFilter => (
+                vec![
+                    "Πατήστε ".green(),
                     "Esc".green().bold(),
-                    " για να διακόψετε την επεξεργασία. ".green().into(),
+                    " για ακύρωση, ".green(),
+                    "Enter".green().bold(),
+                    " για να εμφανίσετε μόνο τις εγγραφές με την ετικέτα".green(),
+                ],
+                Style::default(),
+            ),
+            InputMode::CurrencyEditing => (
+                vec![
+                    "Κωδικός νομίσματος, π.χ. EUR, USD, GBP. Πατήστε ".green(),
                     "Enter".green().bold(),
-                    " για να καταγράψετε τα έξοδα".green().into(),
+                    " για επιστροφή".green(),
                 ],
                 Style::default(),
             ),
             InputMode::DateEditing => (
                 vec![
-                    "Πατήστε ".green().into(),
+                    "Πατήστε ".green(),
                     "Esc".green().bold(),
-                    " για να σταματήσετε την επεξεργασία, ".green().into(),
+                    " για να σταματήσετε την επεξεργασία, ".green(),
                     "Enter".green().bold(),
-                    " για να καταγράψετε την ημερομηνία".green().into(),
+                    " για να καταγράψετε την ημερομηνία (π.χ. 2025-03-14, today, yesterday, -3, 14, 14/3, fri)".green(),
                 ],
                 Style::default(),
             ),
@@ -0,0 +1,324 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use indexmap::IndexMap;
+use crate::error::{BuldakError, Result};
+use crate::model::{Category, Record, RecordsHolder};
+use crate::money::Money;
+use crate::period::Period;
+use crate::repository::Repository;
+
+/// Keys of the saved account mapping in the settings, changed with `buldak export ... --save`.
+const FUNDING_SETTING: &str = "journal_funding";
+/// `key=Account` pairs separated by newlines.
+const ACCOUNTS_SETTING: &str = "journal_accounts";
+
+const DEFAULT_FUNDING: &str = "Assets:Cash";
+const BEANCOUNT_ROOTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+/// Plain-text accounting formats `buldak export --format` can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalFormat {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl FromStr for JournalFormat {
+    type Err = BuldakError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ledger" => Ok(JournalFormat::Ledger),
+            "hledger" => Ok(JournalFormat::Hledger),
+            "beancount" => Ok(JournalFormat::Beancount),
+            _ => Err(BuldakError::Config(format!("unsupported export format '{}'", s))),
+        }
+    }
+}
+
+impl fmt::Display for JournalFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalFormat::Ledger => write!(f, "ledger"),
+            JournalFormat::Hledger => write!(f, "hledger"),
+            JournalFormat::Beancount => write!(f, "beancount"),
+        }
+    }
+}
+
+/// Which account each category is booked to and which account paid for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalOptions {
+    pub funding: String,
+    /// Account per category key; categories left out go to `Expenses:<Key>`.
+    pub accounts: IndexMap<String, String>,
+    /// Beancount `open` directives for the accounts, dated at the start of the period.
+    /// A ledger must open each account only once, so exports appended to one that
+    /// already has them leave them out with `--no-open`. Not saved.
+    pub open_accounts: bool,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        JournalOptions { funding: DEFAULT_FUNDING.to_string(), accounts: IndexMap::new(), open_accounts: true }
+    }
+}
+
+impl JournalOptions {
+    /// The saved mapping, falling back to [`JournalOptions::default`] when none was saved.
+    pub fn load(repo: &dyn Repository) -> Result<Self> {
+        let mut options = JournalOptions::default();
+        if let Some(funding) = repo.setting(FUNDING_SETTING)? {
+            options.set("funding", &funding)?;
+        }
+        if let Some(accounts) = repo.setting(ACCOUNTS_SETTING)? {
+            for pair in accounts.lines() {
+                options.set("account", pair)?;
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn save(&self, repo: &dyn Repository) -> Result<()> {
+        let accounts = self.accounts.iter()
+            .map(|(key, account)| format!("{}={}", key, account))
+            .collect::<Vec<String>>()
+            .join("\n");
+        repo.set_setting(FUNDING_SETTING, &self.funding)?;
+        repo.set_setting(ACCOUNTS_SETTING, &accounts)
+    }
+
+    /// Changes one option by its command line name, `--funding Assets:Bank` or
+    /// `--account store=Expenses:Groceries`.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "funding" => self.funding = check_account(value)?,
+            "account" => {
+                let (key, account) = value.split_once('=').ok_or_else(|| {
+                    BuldakError::Config(format!("--account expects <category key>=<account>, got '{}'", value))
+                })?;
+                self.accounts.insert(key.trim().to_string(), check_account(account)?);
+            }
+            _ => return Err(BuldakError::Config(format!("unknown journal option '{}'", name))),
+        }
+        Ok(())
+    }
+
+    /// Every mapped key has to name a category, a typo would silently book to the default account.
+    pub fn check_keys(&self, categories: &[Category]) -> Result<()> {
+        match self.accounts.keys().find(|key| !categories.iter().any(|c| &c.key == *key)) {
+            Some(key) => Err(BuldakError::UnknownCategory(key.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn account(&self, category: &Category) -> String {
+        match self.accounts.get(&category.key) {
+            Some(account) => account.clone(),
+            None => format!("Expenses:{}", capitalize(&category.key)),
+        }
+    }
+}
+
+fn check_account(account: &str) -> Result<String> {
+    let account = account.trim();
+    if account.split(':').any(|part| part.is_empty() || part.contains(char::is_whitespace) || part.contains(';')) {
+        return Err(BuldakError::Config(format!("invalid account name '{}', expected e.g. Expenses:Groceries", account)));
+    }
+    Ok(account.to_string())
+}
+
+/// Beancount only takes accounts under one of its five roots with capitalised ascii parts.
+fn check_beancount_account(account: &str) -> Result<()> {
+    let mut parts = account.split(':');
+    let root_ok = parts.next().is_some_and(|root| BEANCOUNT_ROOTS.contains(&root));
+    let parts_ok = parts.all(|part| {
+        part.starts_with(|c: char| c.is_ascii_uppercase() || c.is_ascii_digit())
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    if root_ok && parts_ok {
+        Ok(())
+    } else {
+        Err(BuldakError::Config(format!(
+            "'{}' is not a beancount account, map it with --account <key>=Expenses:Name",
+            account
+        )))
+    }
+}
+
+fn capitalize(key: &str) -> String {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The comment on one line without double spaces, which ledger would read as a note.
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Writes one balanced transaction per record, oldest first. Only the records decide the
+/// output, so exporting an unchanged range again gives the same bytes.
+pub fn write_journal<W: Write>(
+    holder: &RecordsHolder,
+    period: &Period,
+    format: JournalFormat,
+    options: &JournalOptions,
+    mut out: W,
+) -> Result<()> {
+    let mut records: Vec<&Record> = holder.records.iter().collect();
+    records.sort_by_key(|r| (r.date, r.id));
+
+    writeln!(out, "; buldak {} export, {}", format, period)?;
+    if format == JournalFormat::Beancount {
+        let mut accounts = BTreeSet::from([options.funding.clone()]);
+        accounts.extend(holder.categories.iter().map(|c| options.account(c)));
+        for account in &accounts {
+            check_beancount_account(account)?;
+        }
+        if options.open_accounts && !records.is_empty() {
+            let (start, _) = period.bounds();
+            writeln!(out)?;
+            for account in &accounts {
+                writeln!(out, "{} open {}", start.format("%Y-%m-%d"), account)?;
+            }
+        }
+    }
+
+    for record in records {
+        let postings: Vec<(String, Money)> = holder.categories.iter()
+            .map(|c| (options.account(c), record.amount(c.id)))
+            .filter(|(_, amount)| !amount.is_zero())
+            .collect();
+        if postings.is_empty() {
+            continue;
+        }
+        let date = record.date.format("%Y-%m-%d");
+        let comment = one_line(&record.comments);
+        let tags = record.tags();
+        writeln!(out)?;
+        match format {
+            JournalFormat::Ledger => {
+                writeln!(out, "{} * {}", date, if comment.is_empty() { "buldak" } else { &comment })?;
+                writeln!(out, "    ; buldak-id: {}", record.id)?;
+                if !tags.is_empty() {
+                    writeln!(out, "    ; :{}:", tags.join(":"))?;
+                }
+            }
+            JournalFormat::Hledger => {
+                writeln!(out, "{} * {}", date, if comment.is_empty() { "buldak" } else { &comment })?;
+                let tags: String = tags.iter().map(|tag| format!(", {}:", tag)).collect();
+                writeln!(out, "    ; buldak-id: {}{}", record.id, tags)?;
+            }
+            JournalFormat::Beancount => {
+                writeln!(out, "{} * \"{}\"", date, comment.replace('\\', "\\\\").replace('"', "\\\""))?;
+                writeln!(out, "  buldak-id: {}", record.id)?;
+            }
+        }
+        let indent = if format == JournalFormat::Beancount { "  " } else { "    " };
+        let total = postings.iter().map(|(_, amount)| *amount).sum::<Money>();
+        for (account, amount) in postings.iter().chain([(options.funding.clone(), -total)].iter()) {
+            writeln!(out, "{}{:<32}  {:>10} {}", indent, account, amount.to_string(), record.currency)?;
+        }
+    }
+    Ok(())
+}
+
+/// Line number of the first difference between a journal on disk and a fresh export,
+/// `None` when they are the same.
+pub fn first_difference(saved: &str, fresh: &str) -> Option<usize> {
+    let (mut saved_lines, mut fresh_lines) = (saved.lines(), fresh.lines());
+    let mut line = 1;
+    loop {
+        match (saved_lines.next(), fresh_lines.next()) {
+            (None, None) => return None,
+            (a, b) if a != b => return Some(line),
+            _ => line += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::repository::memory::InMemoryRepository;
+
+    fn export(repo: &InMemoryRepository, format: JournalFormat, options: &JournalOptions) -> Result<String> {
+        let period = Period::Month(NaiveDate::from_ymd_opt(2025, 3, 1).unwrap());
+        let mut out = Vec::new();
+        write_journal(&repo.records_for_period(&period)?, &period, format, options, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn repo() -> InMemoryRepository {
+        let repo = InMemoryRepository::new();
+        for (day, amounts, comments) in [(9, vec![(3, "4")], ""), (2, vec![(1, "12.50"), (2, "3")], "lidl \"big\"  #weekly")] {
+            let date = NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+            let amounts = amounts.into_iter().map(|(id, amount)| (id, amount.parse().unwrap())).collect();
+            repo.save_record(&Record { id: 0, amounts, comments: comments.to_string(), date, currency: "EUR".to_string(), rate: None }).unwrap();
+        }
+        repo
+    }
+
+    #[test]
+    fn test_ledger_and_hledger() {
+        let repo = repo();
+        let mut options = JournalOptions::default();
+        options.set("account", "store=Expenses:Groceries").unwrap();
+        options.set("funding", "Assets:Bank").unwrap();
+        let ledger = export(&repo, JournalFormat::Ledger, &options).unwrap();
+        assert_eq!(ledger, "; buldak ledger export, Mar-2025\n\
+            \n\
+            2025-03-02 * lidl \"big\" #weekly\n    \
+                ; buldak-id: 2\n    \
+                ; :weekly:\n    \
+                Expenses:Groceries                     12.50 EUR\n    \
+                Expenses:Beer                           3.00 EUR\n    \
+                Assets:Bank                           -15.50 EUR\n\
+            \n\
+            2025-03-09 * buldak\n    \
+                ; buldak-id: 1\n    \
+                Expenses:Allos                          4.00 EUR\n    \
+                Assets:Bank                            -4.00 EUR\n");
+        let hledger = export(&repo, JournalFormat::Hledger, &options).unwrap();
+        assert!(hledger.contains("    ; buldak-id: 2, weekly:\n"));
+        // the same records give the same journal
+        assert_eq!(first_difference(&ledger, &export(&repo, JournalFormat::Ledger, &options).unwrap()), None);
+        assert_eq!(first_difference(&ledger, &hledger), Some(1));
+    }
+
+    #[test]
+    fn test_beancount_accounts() {
+        let repo = repo();
+        let text = export(&repo, JournalFormat::Beancount, &JournalOptions::default()).unwrap();
+        assert!(text.contains("\n2025-03-01 open Assets:Cash\n2025-03-01 open Expenses:Allos\n"), "opened at the start of the month");
+        assert!(text.contains("2025-03-02 * \"lidl \\\"big\\\" #weekly\"\n  buldak-id: 2\n  Expenses:Store "));
+        let appended = export(&repo, JournalFormat::Beancount, &JournalOptions { open_accounts: false, ..Default::default() }).unwrap();
+        assert!(!appended.contains(" open "));
+        assert!(appended.contains("2025-03-02 * \"lidl"));
+
+        let mut options = JournalOptions::default();
+        options.set("account", "beer=Drinks:Beer").unwrap();
+        assert!(export(&repo, JournalFormat::Beancount, &options).is_err());
+        assert!(options.set("funding", "Assets:My Bank").is_err());
+        options.set("account", "wine=Expenses:Wine").unwrap();
+        assert!(matches!(options.check_keys(&repo.categories().unwrap()), Err(BuldakError::UnknownCategory(_))));
+    }
+
+    #[test]
+    fn test_mapping_saved_in_settings() {
+        let repo = InMemoryRepository::new();
+        assert_eq!(JournalOptions::load(&repo).unwrap(), JournalOptions::default());
+        let mut options = JournalOptions::default();
+        options.set("account", "store=Expenses:Food:Groceries").unwrap();
+        options.set("account", "beer=Expenses:Fun").unwrap();
+        options.set("funding", "Liabilities:Card").unwrap();
+        options.save(&repo).unwrap();
+        assert_eq!(JournalOptions::load(&repo).unwrap(), options);
+    }
+}
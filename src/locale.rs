@@ -0,0 +1,236 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::error::{BuldakError, Result};
+use crate::money::Money;
+use crate::repository::Repository;
+
+/// Key of the chosen locale in the settings, changed with `buldak locale <name>`.
+const LOCALE_SETTING: &str = "locale";
+
+/// How amounts are typed and shown: the decimal and thousands separators and where the
+/// currency symbol goes. Scripting commands and exports always write plain numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// `1234.56`, the base currency left out as buldak always did; `,` reads as a decimal point.
+    #[default]
+    Plain,
+    /// `1.234,56 €`
+    Greek,
+    /// `€1,234.56`
+    English,
+}
+
+impl FromStr for Locale {
+    type Err = BuldakError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "plain" => Ok(Locale::Plain),
+            "el" | "gr" => Ok(Locale::Greek),
+            "en" => Ok(Locale::English),
+            _ => Err(BuldakError::Config(format!("unknown locale '{}', expected plain, el or en", s))),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Locale::Plain => write!(f, "plain"),
+            Locale::Greek => write!(f, "el"),
+            Locale::English => write!(f, "en"),
+        }
+    }
+}
+
+impl Locale {
+    /// The saved locale, [`Locale::Plain`] when none was chosen.
+    pub fn load(repo: &dyn Repository) -> Result<Self> {
+        repo.setting(LOCALE_SETTING)?.map_or(Ok(Locale::Plain), |name| name.parse())
+    }
+
+    pub fn save(&self, repo: &dyn Repository) -> Result<()> {
+        repo.set_setting(LOCALE_SETTING, &self.to_string())
+    }
+
+    fn decimal(self) -> char {
+        match self {
+            Locale::Greek => ',',
+            Locale::Plain | Locale::English => '.',
+        }
+    }
+
+    fn thousands(self) -> Option<char> {
+        match self {
+            Locale::Plain => None,
+            Locale::Greek => Some('.'),
+            Locale::English => Some(','),
+        }
+    }
+
+    /// `1.234,56` without a currency, also what an amount field is filled with.
+    pub fn number(self, amount: Money) -> String {
+        let plain = amount.abs().to_string();
+        let (units, cents) = plain.split_once('.').unwrap_or((&plain, "00"));
+        let sign = if amount.is_negative() { "-" } else { "" };
+        format!("{}{}{}{}", sign, self.group(units), self.decimal(), cents)
+    }
+
+    /// The amount with the symbol of its currency, e.g. `12,50 €` or `$3.00`; plain keeps the code.
+    pub fn money(self, amount: Money, currency: &str) -> String {
+        self.with_symbol(&self.number(amount.abs()), amount.is_negative(), currency)
+    }
+
+    /// A table cell: the plain locale leaves the base currency out, the others always show it.
+    pub fn cell(self, amount: Money, currency: &str, base_currency: &str) -> String {
+        if self == Locale::Plain && currency == base_currency {
+            amount.to_string()
+        } else {
+            self.money(amount, currency)
+        }
+    }
+
+    /// Rounded to whole units for the chart bars, e.g. `1.235 €`; plain shows only the number.
+    pub fn rounded(self, amount: Money, currency: &str) -> String {
+        let cents = amount.cents().abs();
+        let units = self.group(&((cents + 50) / 100).to_string());
+        let negative = amount.is_negative() && cents >= 50;
+        match self {
+            Locale::Plain => format!("{}{}", if negative { "-" } else { "" }, units),
+            _ => self.with_symbol(&units, negative, currency),
+        }
+    }
+
+    fn with_symbol(self, unsigned: &str, negative: bool, currency: &str) -> String {
+        let sign = if negative { "-" } else { "" };
+        match (self, symbol(currency)) {
+            (Locale::Plain, _) => format!("{}{} {}", sign, unsigned, currency),
+            (Locale::English, Some(symbol)) => format!("{}{}{}", sign, symbol, unsigned),
+            (Locale::English, None) => format!("{} {}{}", currency, sign, unsigned),
+            (_, symbol) => format!("{}{} {}", sign, unsigned, symbol.unwrap_or(currency)),
+        }
+    }
+
+    fn group(self, units: &str) -> String {
+        let Some(separator) = self.thousands() else { return units.to_string() };
+        let mut grouped = String::new();
+        for (i, digit) in units.chars().enumerate() {
+            if i > 0 && (units.len() - i).is_multiple_of(3) {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// Turns a typed number such as `1.234,56` into `1234.56`. With both separators the
+    /// last one is the decimal point. A lone thousands separator followed by groups of
+    /// three digits groups them, so `1.500` is 1500 in Greek; otherwise it is a decimal point.
+    /// Grouping after a leading zero, as in `0.500`, is rejected as ambiguous.
+    pub fn normalize(self, text: &str) -> Option<String> {
+        let is_separator = |c: char| c == '.' || c == ',';
+        let (decimal, thousands) = match text.rfind(is_separator) {
+            None => (None, None),
+            Some(last) => {
+                let last = text[last..].chars().next()?;
+                let other = if last == '.' { ',' } else { '.' };
+                if text.contains(other) {
+                    (Some(last), Some(other))
+                } else if self.thousands() == Some(last) && is_grouped(text, last) {
+                    (None, Some(last))
+                } else {
+                    (Some(last), None)
+                }
+            }
+        };
+        if thousands.is_some() && text.starts_with('0') {
+            return None;
+        }
+        let (units, cents) = match decimal {
+            Some(decimal) => text.split_once(decimal)?,
+            None => (text, ""),
+        };
+        if cents.contains(is_separator) {
+            return None;
+        }
+        let units = match thousands {
+            Some(thousands) if is_grouped(units, thousands) => units.replace(thousands, ""),
+            Some(_) => return None,
+            None => units.to_string(),
+        };
+        Some(format!("{}.{}", units, cents))
+    }
+}
+
+/// `1.234.567`: one to three digits, then groups of exactly three.
+fn is_grouped(text: &str, separator: char) -> bool {
+    let groups: Vec<&str> = text.split(separator).collect();
+    groups.len() > 1
+        && (1..=3).contains(&groups[0].len())
+        && groups[1..].iter().all(|group| group.len() == 3)
+        && groups.iter().all(|group| group.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn symbol(currency: &str) -> Option<&'static str> {
+    match currency {
+        "EUR" => Some("€"),
+        "USD" => Some("$"),
+        "GBP" => Some("£"),
+        "JPY" => Some("¥"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::InMemoryRepository;
+
+    fn m(amount: &str) -> Money {
+        amount.parse().unwrap()
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(Locale::Plain.money(m("1234.5"), "EUR"), "1234.50 EUR");
+        assert_eq!(Locale::Plain.cell(m("1234.5"), "EUR", "EUR"), "1234.50");
+        assert_eq!(Locale::Greek.cell(m("1234567.5"), "EUR", "EUR"), "1.234.567,50 €");
+        assert_eq!(Locale::Greek.money(m("-12"), "CHF"), "-12,00 CHF");
+        assert_eq!(Locale::English.money(m("1234.5"), "USD"), "$1,234.50");
+        assert_eq!(Locale::English.money(m("-0.5"), "EUR"), "-€0.50");
+        assert_eq!(Locale::English.money(m("999"), "CHF"), "CHF 999.00");
+        assert_eq!(Locale::Greek.rounded(m("1234.5"), "EUR"), "1.235 €");
+        assert_eq!(Locale::Plain.rounded(m("-0.4"), "EUR"), "0");
+        assert_eq!(Locale::English.rounded(m("-1234.5"), "EUR"), "-€1,235");
+        assert_eq!(Locale::Greek.number(m("1234.5")), "1.234,50");
+    }
+
+    #[test]
+    fn test_normalize_typed_numbers() {
+        assert_eq!(Locale::Plain.normalize("1,56").as_deref(), Some("1.56"));
+        assert_eq!(Locale::Plain.normalize("1.500").as_deref(), Some("1.500"));
+        assert_eq!(Locale::Plain.normalize("1,234.56").as_deref(), Some("1234.56"));
+        assert_eq!(Locale::Greek.normalize("1.500").as_deref(), Some("1500."));
+        assert_eq!(Locale::Greek.normalize("1.234,56").as_deref(), Some("1234.56"));
+        assert_eq!(Locale::Greek.normalize("12.5").as_deref(), Some("12.5"));
+        assert_eq!(Locale::English.normalize("1,234").as_deref(), Some("1234."));
+        assert_eq!(Locale::English.normalize("1,5").as_deref(), Some("1.5"));
+        assert_eq!(Locale::Greek.normalize("12").as_deref(), Some("12."));
+        assert_eq!(Locale::Greek.normalize("1.2.3"), None);
+        assert_eq!(Locale::Greek.normalize("12.34,5,6"), None);
+        assert_eq!(Locale::Greek.normalize("1.23,5"), None);
+        assert_eq!(Locale::Greek.normalize("0.500"), None);
+        assert_eq!(Locale::Greek.normalize("0.123,5"), None);
+        assert_eq!(Locale::English.normalize("0,500"), None);
+        assert_eq!(Locale::Greek.normalize("0,500").as_deref(), Some("0.500"));
+    }
+
+    #[test]
+    fn test_locale_saved_in_settings() {
+        let repo = InMemoryRepository::new();
+        assert_eq!(Locale::load(&repo).unwrap(), Locale::Plain);
+        "EL".parse::<Locale>().unwrap().save(&repo).unwrap();
+        assert_eq!(Locale::load(&repo).unwrap(), Locale::Greek);
+        assert!("fr".parse::<Locale>().is_err());
+    }
+}
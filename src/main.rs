@@ -1,52 +1,159 @@
 mod db_repo;
+mod error;
+mod export;
+mod expression;
+mod import;
+mod journal;
+mod locale;
+mod backup;
+mod budget;
+mod calendar;
+mod categories;
 mod table;
 mod chart;
+mod cli;
+mod config;
+mod currency;
 mod inputs;
 mod input_validator;
+mod migrations;
+mod model;
+mod money;
+mod period;
+mod popup;
+mod recurring;
+mod recurring_screen;
+mod repository;
+mod search;
+mod split;
 mod tabs;
+mod undo;
+mod view;
 
+use std::fs::File;
+use std::process::ExitCode;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use chrono::NaiveDate;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
 use color_eyre::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{DefaultTerminal, Frame, text::Line};
 use ratatui::style::{Stylize};
-use ratatui::layout::{Constraint, Layout};
+use ratatui::layout::{Constraint, Layout, Rect};
 use ratatui::prelude::Span;
 use ratatui::widgets::{TableState};
-use crate::chart::vertical_barchart;
-use crate::db_repo::{delete_all, init_db};
+use crate::calendar::CalendarAction;
+use crate::categories::{CategoriesAction, CategoriesState};
+use crate::backup::{create_backup, prune_backups};
+use crate::chart::render_charts;
+use crate::config::{ensure_parent_dir, resolve_db_path, Args};
+use crate::db_repo::SqliteRepository;
+use crate::export::{export_file_name, write_csv, CsvOptions};
+use crate::locale::Locale;
+use crate::model::{Record, RecordFilter};
+use crate::repository::Repository;
 use crate::inputs::{InputMode, InputsState};
-use crate::table::render_table;
+use crate::table::{render_delete_confirmation, render_table};
+use crate::undo::UndoAction;
 use crate::tabs::{render_tabs, TabsState};
+use crate::view::PeriodView;
+use crate::period::{Period, PeriodKind};
+use crate::recurring_screen::{RecurringAction, RecurringState};
+use crate::search::{SearchAction, SearchState};
 
-fn main() -> color_eyre::Result<()> {
-    init_db();
+fn main() -> color_eyre::Result<ExitCode> {
     color_eyre::install()?;
+    let args = Args::from_env().map_err(|err| eyre!(err))?;
+    let db_path = resolve_db_path(&args).map_err(|err| eyre!(err))?;
+    ensure_parent_dir(&db_path)?;
+    match args.command.as_deref() {
+        None => {}
+        Some("restore") => return cli::restore(&db_path, &args.command_args).map(|()| ExitCode::SUCCESS),
+        Some("rates") => return cli::rates(&db_path, &args.command_args).map(|()| ExitCode::SUCCESS),
+        Some("tags") => return cli::tags(&db_path, &args.command_args).map(|()| ExitCode::SUCCESS),
+        Some("locale") => return cli::locale(&db_path, &args.command_args).map(|()| ExitCode::SUCCESS),
+        Some("add") => return Ok(cli::exit_code(cli::add(&db_path, &args.command_args))),
+        Some("list") => return Ok(cli::exit_code(cli::list(&db_path, &args.command_args))),
+        Some("total") => return Ok(cli::exit_code(cli::total(&db_path, &args.command_args))),
+        Some("export") => return Ok(cli::exit_code(cli::export(&db_path, &args.command_args))),
+        Some("import") => return Ok(cli::exit_code(cli::import(&db_path, &args.command_args))),
+        Some(other) => return Err(eyre!("unknown command: {}", other)),
+    }
+    let backup = if db_path.exists() {
+        create_backup(&db_path).and_then(|_| prune_backups(&db_path, args.retention)).map(|_| ())
+    } else {
+        Ok(())
+    };
+    let repo = Rc::new(SqliteRepository::open(&db_path)?);
+    let created = repo.materialize_recurring(Utc::now().date_naive());
+    let mut app = App::new(repo);
+    match created {
+        Ok(0) => {}
+        Ok(count) => app.show_info(format!("Δημιουργήθηκαν {} επαναλαμβανόμενες εγγραφές", count)),
+        Err(err) => app.show_error(err.to_string()),
+    }
+    if let Err(err) = backup {
+        app.show_error(format!("Δεν δημιουργήθηκε αντίγραφο ασφαλείας: {}", err));
+    }
     let terminal = ratatui::init();
-    let result = App::new().run(terminal);
+    let result = app.run(terminal);
     ratatui::restore();
-    result
+    result.map(|()| ExitCode::SUCCESS)
 }
 
 /// The main application which holds the state and logic of the application.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct App {
     running: bool,
-    current_month: Arc<Mutex<NaiveDate>>,
+    /// Where records and categories are read from and written to.
+    repo: Rc<dyn Repository>,
+    current_period: Arc<Mutex<Period>>,
+    /// Cached records of `current_period`, reloaded by [`App::sync_view`].
+    view: PeriodView,
+    /// Tag chosen with `t`, applies to every tab until cleared.
+    filter: RecordFilter,
+    /// Set by every write so the next [`App::sync_view`] reloads months and records.
+    stale: bool,
+    /// Deletes and edits of this session, most recent last.
+    undo_stack: Vec<UndoAction>,
+    /// Record waiting for the user to confirm its deletion.
+    pending_delete: Option<Record>,
+    /// Message in the status bar, cleared by the next key press.
+    status: Option<Status>,
+    /// How amounts are shown, chosen with `buldak locale`.
+    locale: Locale,
+}
+
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub message: String,
+    pub is_error: bool,
 }
 
 impl App {
     /// Construct a new instance of [`App`].
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(repo: Rc<dyn Repository>) -> Self {
+        let period = Period::containing(PeriodKind::Month, Utc::now().date_naive());
+        Self {
+            running: false,
+            repo,
+            current_period: Arc::new(Mutex::new(period)),
+            view: PeriodView::empty(period),
+            filter: RecordFilter::default(),
+            stale: true,
+            undo_stack: Vec::new(),
+            pending_delete: None,
+            status: None,
+            locale: Locale::Plain,
+        }
     }
 
     /// Run the application's main loop.
     pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         self.running = true;
         //TabsState
-        let mut tabs_state = TabsState::new(self.clone());
+        let mut tabs_state = TabsState::new(&self)?;
 
         //Table
         let mut table_state = TableState::default();
@@ -54,21 +161,57 @@ impl App {
         table_state.select_first_column();
 
         //Inputs
-        let mut inputs_state = InputsState::new();
+        self.locale = Locale::load(self.repo.as_ref())?;
+        let mut inputs_state = InputsState::new(self.repo.categories()?, &self.repo.base_currency()?, self.locale);
+
+        //Categories screen
+        let mut categories_state = CategoriesState::new();
+        let reloaded = categories_state.reload(self.repo.as_ref());
+        self.report(reloaded);
+
+        //Recurring rules screen
+        let mut recurring_state = RecurringState::new();
+
+        //Search prompt
+        let mut search_state = SearchState::new();
 
         while self.running {
-            terminal.draw(|frame| self.render(frame, &mut table_state, &mut inputs_state, &mut tabs_state))?;
-            self.handle_crossterm_events(&mut table_state , &mut inputs_state , &mut tabs_state )?;
+            self.sync_view(&mut tabs_state);
+            terminal.draw(|frame| self.render(frame, &mut table_state, &mut inputs_state, &mut tabs_state, &mut categories_state, &mut recurring_state, &mut search_state))?;
+            self.handle_crossterm_events(&mut table_state , &mut inputs_state , &mut tabs_state, &mut categories_state, &mut recurring_state, &mut search_state)?;
         }
         Ok(())
     }
 
-    fn render(&mut self, frame: &mut Frame, table_state : &mut TableState, inputs_state: &mut InputsState, tabs_state: &mut TabsState) {
+    /// Reloads the cached period after a write, a tab switch or a new filter; does nothing otherwise.
+    fn sync_view(&mut self, tabs_state: &mut TabsState) {
+        let period = *self.current_period.lock().unwrap();
+        if !self.stale && self.view.period == period && self.view.filter == self.filter {
+            return;
+        }
+        if self.stale {
+            let refreshed = tabs_state.refresh(self.repo.as_ref());
+            self.report(refreshed);
+        }
+        if let Some(view) = self.report(PeriodView::load(self.repo.as_ref(), period, &self.filter)) {
+            self.view = view;
+            self.stale = false;
+        }
+    }
+
+    /// Marks the cached months and records as out of date after a write.
+    fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(&mut self, frame: &mut Frame, table_state : &mut TableState, inputs_state: &mut InputsState, tabs_state: &mut TabsState, categories_state: &mut CategoriesState, recurring_state: &mut RecurringState, search_state: &mut SearchState) {
 
         let main = Layout::vertical([
             Constraint::Length(1),
             Constraint::Length(3),
             Constraint::Fill(1),
+            Constraint::Length(1),
         ]).split(frame.area());
 
         let inner = Layout::vertical([
@@ -77,25 +220,44 @@ impl App {
             Constraint::Fill(1),
         ]).split(main[2]);
 
-        let title = Line::from_iter([
-            Span::from(format!("+++++ BULDAK expences +++++")).green().bold().underlined(),
+        let mut title = Line::from_iter([
+            Span::from("+++++ BULDAK expences +++++".to_string()).green().bold().underlined(),
         ]);
+        if let Some(tag) = &self.filter.tag {
+            title.push_span(Span::from(format!("  #{}", tag)).yellow().bold());
+        }
         frame.render_widget(title.centered(), main[0]);
         frame.render_widget(render_tabs(tabs_state), main[1]);
         //Table needs to maintain its own state (cursor movements so on)
-        render_table(frame, inner[0], table_state, (self.current_month.lock().unwrap()).to_owned());
+        render_table(frame, inner[0], table_state, &self.view.holder, self.locale);
         inputs_state.render(frame, inner[1]);
-        frame.render_widget(vertical_barchart(self.current_month.lock().unwrap().to_owned()), inner[2]);
+        render_charts(frame, inner[2], &self.view.holder, &self.view.period, self.locale);
+        self.render_status(frame, main[3]);
+        match inputs_state.input_mode {
+            InputMode::Categories => categories_state.render(frame, frame.area()),
+            InputMode::Recurring => recurring_state.render(frame, frame.area()),
+            InputMode::Search => search_state.render(frame, frame.area(), self.locale),
+            InputMode::Calendar => inputs_state.calendar.render(frame, frame.area(), self.locale),
+            InputMode::ConfirmDelete => if let Some(record) = &self.pending_delete {
+                render_delete_confirmation(frame, record, self.locale);
+            },
+            InputMode::RangeEditing => inputs_state.render_range_popup(frame),
+            InputMode::TagFilter => inputs_state.render_tag_popup(frame, &self.view.holder.base_currency),
+            _ => {}
+        }
     }
 
     /// Reads the crossterm events and updates the state of [`App`].
     ///
     /// If your application needs to perform work in between handling events, you can use the
     /// [`event::poll`] function to check if there are any events available with a timeout.
-    fn handle_crossterm_events(&mut self, table_state: &mut TableState, inputs_state: &mut InputsState, tabs_state: &mut TabsState) -> Result<()> {
+    fn handle_crossterm_events(&mut self, table_state: &mut TableState, inputs_state: &mut InputsState, tabs_state: &mut TabsState, categories_state: &mut CategoriesState, recurring_state: &mut RecurringState, search_state: &mut SearchState) -> Result<()> {
         match event::read()? {
             // it's important to check KeyEventKind::Press to avoid handling key release events
-            Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key_event(key, table_state, inputs_state, tabs_state),
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                self.status = None;
+                self.on_key_event(key, table_state, inputs_state, tabs_state, categories_state, recurring_state, search_state)
+            }
             Event::Mouse(_) => {}
             Event::Resize(_, _) => {}
             _ => {}
@@ -103,12 +265,13 @@ impl App {
         Ok(())
     }
 
-    fn on_key_event(&mut self, key: KeyEvent, table_state: &mut TableState, inputs_state: &mut InputsState, tabs_state: &mut TabsState) {
+    #[allow(clippy::too_many_arguments)]
+    fn on_key_event(&mut self, key: KeyEvent, table_state: &mut TableState, inputs_state: &mut InputsState, tabs_state: &mut TabsState, categories_state: &mut CategoriesState, recurring_state: &mut RecurringState, search_state: &mut SearchState) {
         match inputs_state.input_mode {
             InputMode::Normal => match (key.modifiers, key.code) {
                 (_, KeyCode::Esc | KeyCode::Char('q')) | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
-                (KeyModifiers::ALT, KeyCode::Right )=> tabs_state.select_next(self.clone()),
-                (KeyModifiers::ALT, KeyCode::Left )=> tabs_state.select_previous(self.clone()),
+                (KeyModifiers::ALT, KeyCode::Right )=> tabs_state.select_next(self),
+                (KeyModifiers::ALT, KeyCode::Left )=> tabs_state.select_previous(self),
                 (_, KeyCode::Down) => table_state.select_next(),
                 (_, KeyCode::Up) => table_state.select_previous(),
                 (_, KeyCode::Right) => table_state.select_next_column(),
@@ -116,30 +279,591 @@ impl App {
                 (_, KeyCode::Char('g')) => table_state.select_first(),
                 (_, KeyCode::Char('G')) => table_state.select_last(),
                 (_, KeyCode::Char('e')) => { inputs_state.input_mode = InputMode::Editing; },
-                (_, KeyCode::Char('d')) => { inputs_state.input_mode = InputMode::DateEditing; },
-                // (_, KeyCode::Char('[')) => { let _ = delete_all(); },
+                (_, KeyCode::Char('d')) => { let opened = inputs_state.open_calendar(self.repo.as_ref());
+                                             self.report(opened); },
+                (_, KeyCode::Char('m')) => { inputs_state.start_currency_editing(); },
+                (_, KeyCode::Enter) => self.edit_selected_record(table_state, inputs_state),
+                (_, KeyCode::Char('x') | KeyCode::Delete) => self.ask_delete_selected_record(table_state, inputs_state),
+                (_, KeyCode::Char('u')) => self.undo(),
+                (_, KeyCode::Char('c')) => self.open_categories(categories_state, inputs_state),
+                (_, KeyCode::Char('v')) => tabs_state.cycle_kind(self),
+                (_, KeyCode::Char('r')) => inputs_state.start_range_editing(&self.view.period),
+                (_, KeyCode::Char('R')) => self.open_recurring(recurring_state, inputs_state),
+                (_, KeyCode::Char('t')) => self.open_tag_filter(inputs_state),
+                (_, KeyCode::Char('E')) => self.export_view(),
+                (_, KeyCode::Char('/')) => { search_state.clear();
+                                             inputs_state.input_mode = InputMode::Search },
                 _ => {}
             }
             InputMode::Editing => match (key.modifiers, key.code) {
                 (_, KeyCode::Esc) => { inputs_state.input_mode = InputMode::Normal;
+                                       inputs_state.cancel_editing();
                                        inputs_state.selected_input_index = 0 },
-                (_, KeyCode::Enter) => { inputs_state.submit_message();
-                                         inputs_state.inputs_to_default();
-                                         inputs_state.input_mode = InputMode::Normal;
-                                         inputs_state.selected_input_index = 0
+                (_, KeyCode::Enter) => match inputs_state.submit_message(self.repo.as_ref()) {
+                    Ok(undo) => { self.undo_stack.extend(undo);
+                                  self.invalidate();
+                                  inputs_state.inputs_to_default();
+                                  inputs_state.input_mode = InputMode::Normal;
+                                  inputs_state.selected_input_index = 0 },
+                    // keep what was typed so the user can fix it
+                    Err(err) => self.show_error(err.to_string()),
                 },
                 (_, KeyCode::Tab) => { inputs_state.move_cursor_to_next_input(); },
                 _ => { inputs_state.input(key); },
             }
             InputMode::DateEditing => match (key.modifiers, key.code) {
-                (_, KeyCode::Esc | KeyCode::Enter) => { inputs_state.input_mode = InputMode::Normal },
+                (_, KeyCode::Esc) => { inputs_state.input_mode = InputMode::Normal },
+                // stay in the field until it holds a date
+                (_, KeyCode::Enter) => match inputs_state.commit_date() {
+                    Ok(()) => { inputs_state.input_mode = InputMode::Normal },
+                    Err(err) => self.show_error(err.to_string()),
+                },
                 _ => { inputs_state.date_input(key); },
             }
+            InputMode::CurrencyEditing => match key.code {
+                KeyCode::Esc | KeyCode::Enter => { inputs_state.input_mode = InputMode::Normal },
+                _ => { inputs_state.currency_input(key); },
+            }
+            InputMode::RangeEditing => match key.code {
+                KeyCode::Esc => { inputs_state.input_mode = InputMode::Normal },
+                KeyCode::Enter => match inputs_state.range() {
+                    Ok(period) => { tabs_state.set_custom(period, self);
+                                    inputs_state.input_mode = InputMode::Normal },
+                    Err(err) => self.show_error(err.to_string()),
+                },
+                _ => { inputs_state.range_input(key); },
+            }
+            InputMode::TagFilter => match key.code {
+                KeyCode::Esc => { inputs_state.input_mode = InputMode::Normal },
+                KeyCode::Enter => { self.filter.tag = inputs_state.tag();
+                                    table_state.select_first();
+                                    inputs_state.input_mode = InputMode::Normal },
+                _ => { inputs_state.tag_input(key); },
+            }
+            InputMode::ConfirmDelete => match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => { self.delete_pending_record();
+                                                         inputs_state.input_mode = InputMode::Normal },
+                KeyCode::Char('n') | KeyCode::Esc => { self.pending_delete = None;
+                                                       inputs_state.input_mode = InputMode::Normal },
+                _ => {}
+            }
+            InputMode::Categories => match self.report(categories_state.on_key(key, self.repo.as_ref())) {
+                None | Some(CategoriesAction::None) => {}
+                Some(CategoriesAction::Changed) => { inputs_state.set_categories(categories_state.categories.clone());
+                                                     self.invalidate(); }
+                Some(CategoriesAction::Close) => { inputs_state.input_mode = InputMode::Normal; }
+            }
+            InputMode::Recurring => match self.report(recurring_state.on_key(key, self.repo.as_ref())) {
+                None | Some(RecurringAction::None) => {}
+                Some(RecurringAction::Changed(created)) => {
+                    if created > 0 {
+                        self.show_info(format!("Δημιουργήθηκαν {} επαναλαμβανόμενες εγγραφές", created));
+                    }
+                    self.invalidate();
+                }
+                Some(RecurringAction::Close) => { inputs_state.input_mode = InputMode::Normal; }
+            }
+            InputMode::Calendar => match self.report(inputs_state.calendar.on_key(key, self.repo.as_ref())) {
+                None | Some(CalendarAction::None) => {}
+                Some(CalendarAction::Pick(date)) => { inputs_state.set_date(date);
+                                                      inputs_state.input_mode = InputMode::Normal }
+                Some(CalendarAction::Type) => inputs_state.start_date_editing(),
+                Some(CalendarAction::Close) => { inputs_state.input_mode = InputMode::Normal }
+            }
+            InputMode::Search => match self.report(search_state.on_key(key, self.repo.as_ref())) {
+                None | Some(SearchAction::None) => {}
+                Some(SearchAction::Jump(record)) => { self.jump_to(&record, tabs_state, table_state);
+                                                      inputs_state.input_mode = InputMode::Normal; }
+                Some(SearchAction::Close) => { inputs_state.input_mode = InputMode::Normal; }
+            }
+        }
+    }
+
+    fn open_categories(&mut self, categories_state: &mut CategoriesState, inputs_state: &mut InputsState) {
+        categories_state.budget_month = self.view.period.start();
+        let reloaded = categories_state.reload(self.repo.as_ref());
+        if self.report(reloaded).is_some() {
+            inputs_state.input_mode = InputMode::Categories;
         }
     }
 
+    fn open_recurring(&mut self, recurring_state: &mut RecurringState, inputs_state: &mut InputsState) {
+        let reloaded = recurring_state.reload(self.repo.as_ref());
+        if self.report(reloaded).is_some() {
+            inputs_state.input_mode = InputMode::Recurring;
+        }
+    }
+
+    /// Opens the tag prompt with what each tag adds up to in the tab on screen.
+    fn open_tag_filter(&mut self, inputs_state: &mut InputsState) {
+        let (from, to) = self.view.period.bounds();
+        if let Some(totals) = self.report(self.repo.tag_totals(from, to)) {
+            inputs_state.start_tag_filter(self.filter.tag.as_deref(), totals);
+        }
+    }
+
+    /// Writes every record of the tab on screen to a CSV file in the working directory, with
+    /// the saved options. A tag filter only narrows the table, not the export.
+    fn export_view(&mut self) {
+        let path = export_file_name(&self.view.period);
+        let (from, to) = self.view.period.bounds();
+        let written = self.repo.records_between(from, to, &RecordFilter::default()).and_then(|holder| {
+            let options = CsvOptions::load(self.repo.as_ref())?;
+            write_csv(&holder, &options, File::create(&path)?)?;
+            Ok(holder.records.len())
+        });
+        if let Some(count) = self.report(written) {
+            self.show_info(format!("Εξήχθησαν {} εγγραφές στο {}", count, path));
+        }
+    }
+
+    /// Shows the month of a search result with its row selected, dropping a tag filter that hides it.
+    fn jump_to(&mut self, record: &Record, tabs_state: &mut TabsState, table_state: &mut TableState) {
+        if !self.filter.matches(record) {
+            self.filter = RecordFilter::default();
+        }
+        tabs_state.select_month(record.date, self);
+        self.sync_view(tabs_state);
+        table_state.select(self.view.holder.records.iter().position(|r| r.id == record.id));
+    }
+
+    /// Loads the record under the table cursor into the inputs for editing.
+    fn edit_selected_record(&mut self, table_state: &TableState, inputs_state: &mut InputsState) {
+        if let Some(record) = self.selected_record(table_state) {
+            inputs_state.load_record(&record);
+        }
+    }
+
+    fn selected_record(&self, table_state: &TableState) -> Option<Record> {
+        self.view.holder.records.get(table_state.selected()?).cloned()
+    }
+
+    fn ask_delete_selected_record(&mut self, table_state: &TableState, inputs_state: &mut InputsState) {
+        if let Some(record) = self.selected_record(table_state) {
+            self.pending_delete = Some(record);
+            inputs_state.input_mode = InputMode::ConfirmDelete;
+        }
+    }
+
+    fn delete_pending_record(&mut self) {
+        if let Some(record) = self.pending_delete.take()
+            && self.report(self.repo.delete_record(record.id)).is_some() {
+            self.show_info("Η εγγραφή διαγράφηκε, πατήστε u για αναίρεση".to_string());
+            self.undo_stack.push(UndoAction::Deleted(record));
+            self.invalidate();
+        }
+    }
+
+    /// Takes back the most recent delete or edit of this session.
+    fn undo(&mut self) {
+        let Some(action) = self.undo_stack.pop() else {
+            self.show_info("Τίποτα για αναίρεση".to_string());
+            return;
+        };
+        match action.undo(self.repo.as_ref()) {
+            Ok(()) => {
+                self.show_info("Αναιρέθηκε".to_string());
+                self.invalidate();
+            }
+            Err(err) => {
+                self.show_error(err.to_string());
+                self.undo_stack.push(action);
+            }
+        }
+    }
+
+    /// Shows the error of a failed operation in the status bar and lets the app keep going.
+    fn report<T>(&mut self, result: error::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.show_error(err.to_string());
+                None
+            }
+        }
+    }
+
+    fn show_error(&mut self, message: String) {
+        self.status = Some(Status { message, is_error: true });
+    }
+
+    fn show_info(&mut self, message: String) {
+        self.status = Some(Status { message, is_error: false });
+    }
+
+    fn render_status(&self, frame: &mut Frame, area: Rect) {
+        let line = match &self.status {
+            Some(Status { message, is_error: true }) => Line::from(format!(" ✗ {}", message)).white().on_red(),
+            Some(Status { message, is_error: false }) => Line::from(format!(" {}", message)).black().on_green(),
+            None => Line::default(),
+        };
+        frame.render_widget(line, area);
+    }
+
     fn quit(&mut self) {
         self.running = false;
     }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::BudgetLevel;
+    use crate::currency::ExchangeRate;
+    use crate::repository::memory::InMemoryRepository;
+
+    struct Harness<'a> {
+        app: App,
+        repo: Rc<InMemoryRepository>,
+        table_state: TableState,
+        inputs_state: InputsState<'a>,
+        tabs_state: TabsState,
+        categories_state: CategoriesState<'a>,
+        recurring_state: RecurringState<'a>,
+        search_state: SearchState<'a>,
+    }
+
+    impl Harness<'_> {
+        fn new() -> Self {
+            let repo = Rc::new(InMemoryRepository::new());
+            let app = App::new(repo.clone());
+            let tabs_state = TabsState::new(&app).unwrap();
+            let mut table_state = TableState::default();
+            table_state.select_first();
+            let inputs_state = InputsState::new(repo.categories().unwrap(), "EUR", Locale::Plain);
+            Harness { app, repo, table_state, inputs_state, tabs_state, categories_state: CategoriesState::new(), recurring_state: RecurringState::new(), search_state: SearchState::new() }
+        }
+
+        fn press(&mut self, code: KeyCode) {
+            self.app.sync_view(&mut self.tabs_state);
+            self.app.on_key_event(KeyEvent::from(code), &mut self.table_state, &mut self.inputs_state,
+                                  &mut self.tabs_state, &mut self.categories_state, &mut self.recurring_state, &mut self.search_state);
+        }
+
+        fn type_text(&mut self, text: &str) {
+            text.chars().for_each(|c| self.press(KeyCode::Char(c)));
+        }
+
+        fn current_records(&self) -> Vec<Record> {
+            let period = *self.app.current_period.lock().unwrap();
+            self.repo.records_for_period(&period).unwrap().records
+        }
+    }
+
+    #[test]
+    fn test_add_record_with_keys() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('e'));
+        h.type_text("10+2.5");
+        h.press(KeyCode::Enter);
+
+        let records = h.current_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].amount(1), "12.50".parse().unwrap());
+        assert!(matches!(h.inputs_state.input_mode, InputMode::Normal));
+    }
+
+    #[test]
+    fn test_invalid_amount_keeps_editing() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('e'));
+        h.type_text("12+abc");
+        h.press(KeyCode::Enter);
+
+        assert!(h.current_records().is_empty());
+        assert!(matches!(h.inputs_state.input_mode, InputMode::Editing));
+        assert!(h.app.status.as_ref().is_some_and(|s| s.is_error));
+    }
+
+    #[test]
+    fn test_split_receipt_preview_and_submit() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('e'));
+        h.type_text("54.20");
+        h.press(KeyCode::Tab);
+        h.type_text("-12");
+        h.press(KeyCode::Tab);
+        h.type_text("-50");
+        assert!(matches!(h.inputs_state.split_preview(), Some(Err(_))), "more taken out than the receipt");
+        let status = |h: &Harness| h.app.status.as_ref().map(|s| s.message.clone()).unwrap_or_default();
+        h.press(KeyCode::Enter);
+        assert!(h.current_records().is_empty());
+        assert!(status(&h).contains("split of 62.00 is more than the receipt total 54.20"), "{}", status(&h));
+
+        h.press(KeyCode::Backspace);
+        let split = h.inputs_state.split_preview().unwrap().unwrap();
+        assert_eq!(split.rest, "37.20".parse().unwrap());
+        h.press(KeyCode::Enter);
+        let record = h.current_records()[0].clone();
+        assert_eq!(record.amounts.values().map(|m| m.to_string()).collect::<Vec<_>>(), ["37.20", "12.00", "5.00"]);
+    }
+
+    #[test]
+    fn test_delete_with_confirmation_and_undo() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('e'));
+        h.type_text("5");
+        h.press(KeyCode::Enter);
+
+        h.press(KeyCode::Char('x'));
+        h.press(KeyCode::Char('n'));
+        assert_eq!(h.current_records().len(), 1);
+
+        h.press(KeyCode::Char('x'));
+        h.press(KeyCode::Char('y'));
+        assert!(h.current_records().is_empty());
+
+        h.press(KeyCode::Char('u'));
+        assert_eq!(h.current_records().len(), 1);
+        assert!(h.app.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn test_view_reloads_only_after_writes_and_month_changes() {
+        let mut h = Harness::new();
+        h.app.sync_view(&mut h.tabs_state);
+        let reads = h.repo.reads.get();
+        h.press(KeyCode::Down);
+        h.press(KeyCode::Up);
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.repo.reads.get(), reads, "moving the cursor must not query");
+
+        h.press(KeyCode::Char('e'));
+        h.type_text("5");
+        h.press(KeyCode::Enter);
+        h.app.sync_view(&mut h.tabs_state);
+        assert!(h.repo.reads.get() > reads);
+        assert_eq!(h.app.view.holder.records.len(), 1);
+
+        let reads = h.repo.reads.get();
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.repo.reads.get(), reads);
+    }
+
+    #[test]
+    fn test_quarter_and_custom_range_views() {
+        let mut h = Harness::new();
+        let today = Utc::now().date_naive();
+        let quarter = Period::containing(PeriodKind::Quarter, today);
+        let (from, to) = quarter.bounds();
+        for date in [from, to] {
+            let amounts = [(1, "2".parse().unwrap())].into_iter().collect();
+            h.repo.save_record(&Record { id: 0, amounts, comments: String::new(), date, currency: "EUR".to_string(), rate: None }).unwrap();
+        }
+        h.app.invalidate();
+
+        h.press(KeyCode::Char('v'));
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.app.view.period, quarter);
+        assert_eq!(h.app.view.holder.records.len(), 2);
+
+        // the prompt starts as "<from>..<to>", replace the end with the first day
+        h.press(KeyCode::Char('r'));
+        (0..10).for_each(|_| h.press(KeyCode::Backspace));
+        h.press(KeyCode::Enter);
+        assert!(matches!(h.inputs_state.input_mode, InputMode::RangeEditing), "incomplete range is refused");
+        h.type_text(&from.format("%Y-%m-%d").to_string());
+        h.press(KeyCode::Enter);
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.app.view.period, Period::Custom { from, to: from });
+        assert_eq!(h.app.view.holder.records.len(), 1);
+        assert_eq!(h.tabs_state.periods.len(), 1);
+
+        h.press(KeyCode::Char('v'));
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.app.view.period, Period::containing(PeriodKind::Month, from));
+    }
+
+    #[test]
+    fn test_add_recurring_rule_creates_due_record() {
+        let mut h = Harness::new();
+        let today = Utc::now().date_naive();
+        h.press(KeyCode::Char('R'));
+        h.press(KeyCode::Char('a'));
+        h.type_text(&format!("monthly {}", today.format("%-d")));
+        h.press(KeyCode::Tab);
+        h.press(KeyCode::Tab);
+        h.type_text("nope=1");
+        h.press(KeyCode::Enter);
+        assert!(h.app.status.as_ref().is_some_and(|s| s.is_error));
+        assert!(h.repo.recurring_rules().unwrap().is_empty());
+
+        (0..6).for_each(|_| h.press(KeyCode::Backspace));
+        h.type_text("allos=450");
+        h.press(KeyCode::Tab);
+        h.type_text("rent");
+        h.press(KeyCode::Enter);
+        assert_eq!(h.repo.recurring_rules().unwrap().len(), 1);
+        assert_eq!(h.current_records().len(), 1);
+        assert_eq!(h.current_records()[0].comments, "rent");
+
+        h.press(KeyCode::Esc);
+        assert!(matches!(h.inputs_state.input_mode, InputMode::Normal));
+    }
+
+    #[test]
+    fn test_budget_set_from_categories_screen() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('c'));
+        h.press(KeyCode::Char('b'));
+        h.type_text("100");
+        h.press(KeyCode::Enter);
+        h.press(KeyCode::Esc);
+        h.press(KeyCode::Char('e'));
+        h.type_text("85");
+        h.press(KeyCode::Enter);
+
+        h.app.sync_view(&mut h.tabs_state);
+        let holder = &h.app.view.holder;
+        assert_eq!(holder.budget(1), Some("100".parse().unwrap()));
+        assert_eq!(BudgetLevel::of(holder.total(1), holder.budget(1)), BudgetLevel::Near);
+
+        // carried over to every month of the quarter
+        h.press(KeyCode::Char('v'));
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.app.view.holder.budget(1), Some("300".parse().unwrap()));
+
+        // shown and prefilled with the separators of the locale
+        Locale::Greek.save(h.repo.as_ref()).unwrap();
+        h.press(KeyCode::Char('c'));
+        h.press(KeyCode::Char('b'));
+        assert_eq!(h.categories_state.name_input.lines()[0], "100,00");
+    }
+
+    #[test]
+    fn test_negative_budget_rejected() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('c'));
+        h.press(KeyCode::Char('b'));
+        h.type_text("-50");
+        h.press(KeyCode::Enter);
+
+        assert!(h.app.status.as_ref().is_some_and(|s| s.is_error));
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.app.view.holder.budget(1), None);
+    }
+
+    #[test]
+    fn test_record_in_foreign_currency() {
+        let mut h = Harness::new();
+        let today = Utc::now().date_naive();
+        h.repo.add_exchange_rates(&[ExchangeRate::new(today, "GBP", "EUR", 1.2).unwrap()]).unwrap();
+        h.press(KeyCode::Char('m'));
+        (0..3).for_each(|_| h.press(KeyCode::Backspace));
+        h.type_text("gbp");
+        h.press(KeyCode::Enter);
+        h.press(KeyCode::Char('e'));
+        h.type_text("10");
+        h.press(KeyCode::Enter);
+
+        h.app.sync_view(&mut h.tabs_state);
+        let holder = &h.app.view.holder;
+        assert_eq!(holder.records[0].currency, "GBP");
+        assert_eq!(holder.all_total, "12".parse().unwrap());
+    }
+
+    #[test]
+    fn test_pick_date_in_calendar() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('d'));
+        assert!(matches!(h.inputs_state.input_mode, InputMode::Calendar));
+        h.press(KeyCode::Left);
+        h.press(KeyCode::PageUp);
+        h.press(KeyCode::Enter);
+        let picked = Utc::now().date_naive() - chrono::Days::new(1) - chrono::Months::new(1);
+        assert!(matches!(h.inputs_state.input_mode, InputMode::Normal));
+        assert_eq!(h.inputs_state.date_input.text_area.lines()[0], picked.format("%Y-%m-%d").to_string());
+    }
+
+    #[test]
+    fn test_date_shorthand_and_invalid_date() {
+        let mut h = Harness::new();
+        h.press(KeyCode::Char('d'));
+        h.press(KeyCode::Tab);
+        h.type_text("-1");
+        h.press(KeyCode::Enter);
+        let yesterday = Utc::now().date_naive() - chrono::Days::new(1);
+        assert_eq!(h.inputs_state.date_input.text_area.lines()[0], yesterday.format("%Y-%m-%d").to_string());
+        h.press(KeyCode::Char('e'));
+        h.type_text("5");
+        h.press(KeyCode::Enter);
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.repo.records_between(yesterday, yesterday, &RecordFilter::default()).unwrap().records.len(), 1);
+
+        h.press(KeyCode::Char('d'));
+        h.press(KeyCode::Tab);
+        h.type_text("31/2");
+        h.press(KeyCode::Enter);
+        assert!(matches!(h.inputs_state.input_mode, InputMode::DateEditing), "an invalid date is not accepted");
+        assert!(h.app.status.as_ref().is_some_and(|s| s.is_error));
+        h.press(KeyCode::Esc);
+        h.press(KeyCode::Char('e'));
+        h.type_text("7");
+        h.press(KeyCode::Enter);
+        assert!(matches!(h.inputs_state.input_mode, InputMode::Editing), "nor is a record with it");
+    }
+
+    #[test]
+    fn test_amounts_typed_in_greek_locale() {
+        let mut h = Harness::new();
+        h.inputs_state.locale = Locale::Greek;
+        h.press(KeyCode::Char('e'));
+        h.type_text("1.500+2,5");
+        h.press(KeyCode::Enter);
+
+        h.app.sync_view(&mut h.tabs_state);
+        let record = h.app.view.holder.records[0].clone();
+        assert_eq!(record.get_day_summary(), "1502.5".parse().unwrap());
+        h.inputs_state.load_record(&record);
+        assert_eq!(h.inputs_state.inputs[0].text_area.lines()[0], "1.502,50");
+    }
+
+    #[test]
+    fn test_filter_by_tag() {
+        let mut h = Harness::new();
+        for (amount, comment) in [("10", "ferry #holiday"), ("4", "#Holiday beach"), ("7", "groceries")] {
+            h.press(KeyCode::Char('e'));
+            h.type_text(amount);
+            h.press(KeyCode::Tab);
+            h.press(KeyCode::Tab);
+            h.press(KeyCode::Tab);
+            h.type_text(comment);
+            h.press(KeyCode::Enter);
+        }
+
+        h.press(KeyCode::Char('t'));
+        assert_eq!(h.inputs_state.tag_totals.get("holiday"), Some(&"14".parse().unwrap()));
+        h.type_text("#holiday");
+        h.press(KeyCode::Enter);
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.app.view.holder.records.len(), 2);
+        assert_eq!(h.app.view.holder.all_total, "14".parse().unwrap());
+
+        h.press(KeyCode::Char('t'));
+        (0.."holiday".len()).for_each(|_| h.press(KeyCode::Backspace));
+        h.press(KeyCode::Enter);
+        h.app.sync_view(&mut h.tabs_state);
+        assert_eq!(h.app.view.holder.records.len(), 3);
+    }
+
+    #[test]
+    fn test_search_jumps_to_month_and_row() {
+        let mut h = Harness::new();
+        let month_ago = Utc::now().date_naive() - chrono::Months::new(1);
+        for (comment, date) in [("pharmacy aspirin", month_ago), ("bread", month_ago), ("beer", Utc::now().date_naive())] {
+            let amounts = [(1, "2".parse().unwrap())].into_iter().collect();
+            h.repo.save_record(&Record { id: 0, amounts, comments: comment.to_string(), date, currency: "EUR".to_string(), rate: None }).unwrap();
+        }
+        h.app.invalidate();
+
+        h.press(KeyCode::Char('/'));
+        h.type_text("brea");
+        assert_eq!(h.search_state.results.len(), 1);
+        h.press(KeyCode::Enter);
+
+        assert!(matches!(h.inputs_state.input_mode, InputMode::Normal));
+        assert_eq!(h.app.view.period, Period::containing(PeriodKind::Month, month_ago));
+        let selected = h.table_state.selected().map(|i| h.app.view.holder.records[i].comments.clone());
+        assert_eq!(selected.as_deref(), Some("bread"));
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt;
+use const_format::concatcp;
+use rusqlite::Connection;
+
+/// A single schema change. Its version is its 1-based position in [`MIGRATIONS`].
+pub struct Migration {
+    pub description: &'static str,
+    pub sql: &'static str,
+    /// The tags and the search index of every record have to be written again after it,
+    /// which needs Rust and is done by [`crate::db_repo::SqliteRepository`] on open.
+    pub reindex: bool,
+}
+
+/// A category every fresh database starts with; its sort order is its id.
+pub struct SeedCategory {
+    pub id: i64,
+    pub key: &'static str,
+    pub name: &'static str,
+    pub colour: &'static str,
+}
+
+/// Categories inserted by the categories migration, also what the in-memory test
+/// repository starts with.
+pub const SEED_CATEGORIES: [SeedCategory; 3] = [
+    SeedCategory { id: 1, key: "store", name: "Προϊόντα", colour: "green" },
+    SeedCategory { id: 2, key: "beer", name: "Μπύρα", colour: "yellow" },
+    SeedCategory { id: 3, key: "allos", name: "Αλλος", colour: "cyan" },
+];
+
+/// Base currency of a fresh database and of records from before currencies existed.
+pub const SEED_BASE_CURRENCY: &str = "EUR";
+
+/// `(id, 'key', 'name', 'colour', sort order)` of one of the [`SEED_CATEGORIES`].
+macro_rules! seed_category_row {
+    ($i:literal) => {
+        concatcp!(
+            "(", SEED_CATEGORIES[$i].id, ", '", SEED_CATEGORIES[$i].key, "', '", SEED_CATEGORIES[$i].name,
+            "', '", SEED_CATEGORIES[$i].colour, "', ", SEED_CATEGORIES[$i].id, ")"
+        )
+    };
+}
+
+/// Ordered list of schema changes. Never edit or reorder an entry that has shipped,
+/// append a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "baseline records table",
+        sql: "CREATE TABLE if not exists records (
+                id    INTEGER PRIMARY KEY,
+                store  FLOAT,
+                beer  FLOAT,
+                allos  FLOAT,
+                comment  TEXT,
+                date  TEXT
+            );",
+        reindex: false,
+    },
+    Migration {
+        description: "store amounts as integer cents",
+        sql: "CREATE TABLE records_cents (
+                id    INTEGER PRIMARY KEY,
+                store  INTEGER NOT NULL DEFAULT 0,
+                beer  INTEGER NOT NULL DEFAULT 0,
+                allos  INTEGER NOT NULL DEFAULT 0,
+                comment  TEXT,
+                date  TEXT
+            );
+            INSERT INTO records_cents (id, store, beer, allos, comment, date)
+                SELECT id,
+                       CAST(ROUND(IFNULL(store, 0) * 100) AS INTEGER),
+                       CAST(ROUND(IFNULL(beer, 0) * 100) AS INTEGER),
+                       CAST(ROUND(IFNULL(allos, 0) * 100) AS INTEGER),
+                       comment,
+                       date
+                FROM records;
+            DROP TABLE records;
+            ALTER TABLE records_cents RENAME TO records;",
+        reindex: false,
+    },
+    Migration {
+        description: "user-defined categories with per-category record lines",
+        sql: concatcp!("CREATE TABLE categories (
+                id  INTEGER PRIMARY KEY,
+                key  TEXT NOT NULL UNIQUE,
+                name  TEXT NOT NULL,
+                colour  TEXT NOT NULL DEFAULT 'green',
+                sort_order  INTEGER NOT NULL,
+                archived  INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO categories (id, key, name, colour, sort_order) VALUES
+                ", seed_category_row!(0), ",
+                ", seed_category_row!(1), ",
+                ", seed_category_row!(2), ";
+            CREATE TABLE record_lines (
+                record_id  INTEGER NOT NULL,
+                category_id  INTEGER NOT NULL,
+                amount  INTEGER NOT NULL,
+                PRIMARY KEY (record_id, category_id)
+            );
+            INSERT INTO record_lines (record_id, category_id, amount)
+                SELECT id, 1, store FROM records WHERE store <> 0
+                UNION ALL SELECT id, 2, beer FROM records WHERE beer <> 0
+                UNION ALL SELECT id, 3, allos FROM records WHERE allos <> 0;
+            CREATE TABLE records_v3 (
+                id    INTEGER PRIMARY KEY,
+                comment  TEXT,
+                date  TEXT
+            );
+            INSERT INTO records_v3 (id, comment, date) SELECT id, comment, date FROM records;
+            DROP TABLE records;
+            ALTER TABLE records_v3 RENAME TO records;"),
+        reindex: false,
+    },
+    Migration {
+        description: "soft delete for records",
+        sql: "ALTER TABLE records ADD COLUMN deleted_at TEXT;",
+        reindex: false,
+    },
+    Migration {
+        description: "index records by date for range queries",
+        sql: "CREATE INDEX records_date ON records (date);",
+        reindex: false,
+    },
+    Migration {
+        description: "recurring rules",
+        sql: "CREATE TABLE recurring_rules (
+                id  INTEGER PRIMARY KEY,
+                comment  TEXT NOT NULL DEFAULT '',
+                schedule  TEXT NOT NULL,
+                start_date  TEXT NOT NULL,
+                last_run  TEXT
+            );
+            CREATE TABLE recurring_lines (
+                rule_id  INTEGER NOT NULL,
+                category_id  INTEGER NOT NULL,
+                amount  INTEGER NOT NULL,
+                PRIMARY KEY (rule_id, category_id)
+            );",
+        reindex: false,
+    },
+    Migration {
+        description: "monthly budgets per category",
+        sql: "CREATE TABLE budgets (
+                category_id  INTEGER NOT NULL,
+                month  TEXT NOT NULL,
+                amount  INTEGER NOT NULL,
+                PRIMARY KEY (category_id, month)
+            );",
+        reindex: false,
+    },
+    Migration {
+        description: "record currencies and exchange rates",
+        sql: concatcp!("ALTER TABLE records ADD COLUMN currency TEXT NOT NULL DEFAULT '", SEED_BASE_CURRENCY, "';
+            ALTER TABLE recurring_rules ADD COLUMN currency TEXT NOT NULL DEFAULT '", SEED_BASE_CURRENCY, "';
+            CREATE TABLE settings (
+                key  TEXT PRIMARY KEY,
+                value  TEXT NOT NULL
+            );
+            INSERT INTO settings (key, value) VALUES ('base_currency', '", SEED_BASE_CURRENCY, "');
+            CREATE TABLE exchange_rates (
+                date  TEXT NOT NULL,
+                from_currency  TEXT NOT NULL,
+                to_currency  TEXT NOT NULL,
+                rate  REAL NOT NULL,
+                PRIMARY KEY (date, from_currency, to_currency)
+            );"),
+        reindex: false,
+    },
+    Migration {
+        description: "tags on records",
+        sql: "CREATE TABLE tags (
+                id  INTEGER PRIMARY KEY,
+                name  TEXT NOT NULL UNIQUE
+            );
+            CREATE TABLE record_tags (
+                record_id  INTEGER NOT NULL,
+                tag_id  INTEGER NOT NULL,
+                PRIMARY KEY (record_id, tag_id)
+            );
+            CREATE INDEX record_tags_tag ON record_tags (tag_id);",
+        reindex: true,
+    },
+    Migration {
+        description: "full-text search over comments and tags",
+        sql: "CREATE VIRTUAL TABLE records_fts USING fts5(comment, tags, tokenize = 'unicode61 remove_diacritics 2');",
+        reindex: true,
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    /// A migration step failed; the whole upgrade was rolled back.
+    Failed { version: u32, description: &'static str, source: rusqlite::Error },
+    /// The database was written by a newer binary and we don't know its schema.
+    TooNew { db_version: u32, supported: u32 },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(err) => write!(f, "migration failed: {}", err),
+            MigrationError::Failed { version, description, source } => {
+                write!(f, "migration {} ({}) failed: {}", version, description, source)
+            }
+            MigrationError::TooNew { db_version, supported } => write!(
+                f,
+                "database schema version {} is newer than the supported version {}",
+                db_version, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(err: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(err)
+    }
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.len() as u32
+}
+
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// Brings the database up to [`latest_version`], applying all pending migrations
+/// in one transaction. Returns the number of migrations applied.
+pub fn migrate(conn: &mut Connection) -> Result<usize, MigrationError> {
+    let current = schema_version(conn)?;
+    let supported = latest_version();
+    if current > supported {
+        return Err(MigrationError::TooNew { db_version: current, supported });
+    }
+    if current == supported {
+        return Ok(0);
+    }
+
+    let tx = conn.transaction()?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = version as u32 + 1;
+        tx.execute_batch(migration.sql).map_err(|source| MigrationError::Failed {
+            version,
+            description: migration.description,
+            source,
+        })?;
+        tx.pragma_update(None, "user_version", version)?;
+    }
+    tx.commit()?;
+    Ok((supported - current) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema as created by `init_db` before migrations existed (user_version 0).
+    fn baseline_fixture() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE records (
+                id    INTEGER PRIMARY KEY,
+                store  FLOAT,
+                beer  FLOAT,
+                allos  FLOAT,
+                comment  TEXT,
+                date  TEXT
+            );
+            INSERT INTO records (store,beer,allos,comment,date) VALUES (12.5, 3.2000000476837158, 0, 'lidl', '2025-03-02');
+            INSERT INTO records (store,beer,allos,comment,date) VALUES (0, 0, 40, 'pharmacy', '2025-03-14');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_migrate_fresh_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&mut conn).unwrap(), MIGRATIONS.len());
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_migrate_baseline_fixture_keeps_records() {
+        let mut conn = baseline_fixture();
+        migrate(&mut conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+
+        let count: i64 = conn.query_row("SELECT count(*) FROM records", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        let comment: String = conn
+            .query_row("SELECT comment FROM records WHERE date = '2025-03-14'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(comment, "pharmacy");
+    }
+
+    #[test]
+    fn test_migrate_moves_amounts_into_category_lines() {
+        let mut conn = baseline_fixture();
+        migrate(&mut conn).unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT c.key, l.amount FROM record_lines l
+                 JOIN records r ON r.id = l.record_id
+                 JOIN categories c ON c.id = l.category_id
+                 WHERE r.comment = 'lidl' ORDER BY c.sort_order",
+            )
+            .unwrap();
+        let lines = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, vec![("store".to_string(), 1250), ("beer".to_string(), 320)]);
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let mut conn = baseline_fixture();
+        migrate(&mut conn).unwrap();
+        assert_eq!(migrate(&mut conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_migrate_refuses_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+        match migrate(&mut conn) {
+            Err(MigrationError::TooNew { db_version, supported }) => {
+                assert_eq!(db_version, latest_version() + 1);
+                assert_eq!(supported, latest_version());
+            }
+            other => panic!("expected TooNew, got {:?}", other),
+        }
+    }
+}
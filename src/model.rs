@@ -0,0 +1,312 @@
+use chrono::{Datelike, NaiveDate};
+use indexmap::IndexMap;
+use ratatui::style::Color;
+use crate::locale::Locale;
+use crate::money::Money;
+
+#[derive(Debug, Clone)]
+pub struct Category {
+    pub id: i64,
+    /// Stable ascii-ish identifier used by scripts, e.g. `store`.
+    pub key: String,
+    pub name: String,
+    pub colour: String,
+    pub sort_order: i64,
+    pub archived: bool,
+}
+
+impl Category {
+    pub fn color(&self) -> Color {
+        self.colour.parse().unwrap_or(Color::Green)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub id: i64,
+    /// Amount per category id, only categories with a line are present.
+    pub amounts: IndexMap<i64, Money>,
+    pub comments: String,
+    pub date: NaiveDate,
+    /// Currency the amounts were paid in, e.g. `USD`.
+    pub currency: String,
+    /// Base currency units per unit of `currency` on `date`. Filled in when records are
+    /// loaded, `None` when no rate is known; ignored when saving.
+    pub rate: Option<f64>,
+}
+
+impl Record {
+    /// Table cells. Amounts stay in the record's currency; the day total is in the base
+    /// currency, followed by the original total when they differ.
+    pub fn vec_of_fields(&self, categories: &[Category], base_currency: &str, locale: Locale) -> Vec<String> {
+        let mut fields = vec!(self.date.format("%Y-%m-%d").to_string());
+        fields.extend(categories.iter().map(|c| match self.amounts.get(&c.id) {
+            Some(amount) => locale.cell(*amount, &self.currency, base_currency),
+            None => locale.cell(Money::ZERO, base_currency, base_currency),
+        }));
+        let original = locale.money(self.get_day_summary(), &self.currency);
+        fields.push(match (self.currency != base_currency, self.base_total()) {
+            (false, _) => locale.cell(self.get_day_summary(), base_currency, base_currency),
+            (true, Some(base)) => format!("{} ({})", locale.cell(base, base_currency, base_currency), original),
+            (true, None) => format!("? ({})", original),
+        });
+        fields.push(self.comments.to_string());
+        fields
+    }
+
+    pub fn amount(&self, category_id: i64) -> Money {
+        self.amounts.get(&category_id).copied().unwrap_or_default()
+    }
+
+    pub fn get_day_summary(&self) -> Money {
+        self.amounts.values().sum()
+    }
+
+    /// Day total in the base currency, `None` without a rate.
+    pub fn base_total(&self) -> Option<Money> {
+        self.rate.map(|rate| self.amounts.values().map(|amount| amount.convert(rate)).sum())
+    }
+
+    /// Tags written as `#word` in the comment.
+    pub fn tags(&self) -> Vec<String> {
+        extract_tags(&self.comments)
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordsHolder {
+    pub records: Vec<Record>,
+    /// Columns to show: active categories plus archived ones still used by these records.
+    pub categories: Vec<Category>,
+    pub totals: IndexMap<i64, Money>,
+    pub all_total : Money,
+    /// Budget per category id over the same span, only categories that have one.
+    pub budgets: IndexMap<i64, Money>,
+    /// Currency of the totals and budgets.
+    pub base_currency: String,
+    /// Currencies of records left out of the totals because they have no rate.
+    pub missing_rates: Vec<String>,
+}
+
+impl RecordsHolder {
+
+    pub fn new(recs: &[Record], categories: &[Category], base_currency: &str) -> RecordsHolder {
+        let totals = Self::calculate_totals(recs);
+        let categories = categories
+            .iter()
+            .filter(|c| !c.archived || recs.iter().any(|r| r.amounts.contains_key(&c.id)))
+            .cloned()
+            .collect();
+        let mut missing_rates: Vec<String> = recs.iter()
+            .filter(|r| r.rate.is_none())
+            .map(|r| r.currency.clone())
+            .collect();
+        missing_rates.sort();
+        missing_rates.dedup();
+        RecordsHolder {
+            records: recs.to_vec(),
+            categories,
+            all_total: totals.values().sum(),
+            totals,
+            budgets: IndexMap::new(),
+            base_currency: base_currency.to_string(),
+            missing_rates,
+        }
+    }
+
+    pub fn with_budgets(mut self, budgets: IndexMap<i64, Money>) -> Self {
+        self.budgets = budgets;
+        self
+    }
+
+    pub fn budget(&self, category_id: i64) -> Option<Money> {
+        self.budgets.get(&category_id).copied()
+    }
+
+    /// Sum of all budgets, `None` when no category has one.
+    pub fn total_budget(&self) -> Option<Money> {
+        if self.budgets.is_empty() { None } else { Some(self.budgets.values().sum()) }
+    }
+
+    /// Spending in the categories that have a budget, what [`RecordsHolder::total_budget`] limits.
+    pub fn budgeted_total(&self) -> Money {
+        self.budgets.keys().map(|category_id| self.total(*category_id)).sum()
+    }
+
+    pub fn total(&self, category_id: i64) -> Money {
+        self.totals.get(&category_id).copied().unwrap_or_default()
+    }
+
+    fn calculate_totals(recs: &[Record]) -> IndexMap<i64, Money> {
+        let mut totals = IndexMap::new();
+        for record in recs {
+            let Some(rate) = record.rate else { continue };
+            for (category_id, amount) in record.amounts.iter() {
+                *totals.entry(*category_id).or_default() += amount.convert(rate);
+            }
+        }
+        totals
+    }
+
+}
+
+/// Narrows a date range query; fields left `None` don't filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordFilter {
+    /// Only records with a line in this category.
+    pub category: Option<i64>,
+    /// Case-insensitive substring of the comment.
+    pub comment: Option<String>,
+    /// Only records tagged with this, lower case without the `#`.
+    pub tag: Option<String>,
+    /// Bounds on the record total, both included.
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+}
+
+impl RecordFilter {
+    pub fn matches(&self, record: &Record) -> bool {
+        let total = record.base_total().unwrap_or_else(|| record.get_day_summary());
+        self.category.is_none_or(|id| record.amounts.contains_key(&id))
+            && self.comment.as_ref().is_none_or(|text| record.comments.to_lowercase().contains(&text.to_lowercase()))
+            && self.tag.as_ref().is_none_or(|tag| record.tags().contains(tag))
+            && self.min_amount.is_none_or(|min| total >= min)
+            && self.max_amount.is_none_or(|max| total <= max)
+    }
+}
+
+pub fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// `#Holiday trip #food, #food` -> `["holiday", "food"]`. Punctuation ends a tag so it can
+/// close a sentence; `-` and `_` are kept for tags like `#car-repair`.
+pub fn extract_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(tag) = word.strip_prefix('#') else { continue };
+        let tag: String = tag
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+            .collect::<String>()
+            .to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Lower case without Greek accents, `Φαρμακείο` -> `φαρμακειο`, so searches don't depend on
+/// how a word was typed. SQLite's own diacritics folding only covers Latin letters.
+pub fn fold_accents(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ά' => 'α',
+            'έ' => 'ε',
+            'ή' => 'η',
+            'ί' | 'ϊ' | 'ΐ' => 'ι',
+            'ό' => 'ο',
+            'ύ' | 'ϋ' | 'ΰ' => 'υ',
+            'ώ' => 'ω',
+            other => other,
+        })
+        .collect()
+}
+
+pub fn category_key(name: &str) -> String {
+    let key = name
+        .trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+    if key.is_empty() { "category".to_string() } else { key }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(id: i64, name: &str, archived: bool) -> Category {
+        Category {
+            id,
+            key: category_key(name),
+            name: name.to_string(),
+            colour: "green".to_string(),
+            sort_order: id,
+            archived,
+        }
+    }
+
+    fn record(amounts: &[(i64, &str)]) -> Record {
+        Record {
+            id: 0,
+            amounts: amounts.iter().map(|(id, a)| (*id, a.parse().unwrap())).collect(),
+            comments: String::new(),
+            date: NaiveDate::from_ymd_opt(2025, 3, 2).unwrap(),
+            currency: "EUR".to_string(),
+            rate: Some(1.0),
+        }
+    }
+
+    #[test]
+    fn test_records_holder_totals_per_category() {
+        let categories = [category(1, "store", false), category(2, "beer", false), category(3, "old", true)];
+        let holder = RecordsHolder::new(&[record(&[(1, "10.10"), (2, "2.20")]), record(&[(1, "0.20")])], &categories, "EUR");
+        assert_eq!(holder.total(1), "10.30".parse().unwrap());
+        assert_eq!(holder.total(2), "2.20".parse().unwrap());
+        assert_eq!(holder.all_total, "12.50".parse().unwrap());
+        // archived and unused in this month, so no column for it
+        assert_eq!(holder.categories.iter().map(|c| c.id).collect::<Vec<_>>(), vec![1, 2]);
+
+        let holder = RecordsHolder::new(&[record(&[(3, "1")])], &categories, "EUR");
+        assert_eq!(holder.categories.len(), 3);
+    }
+
+    #[test]
+    fn test_budgeted_total_leaves_out_unbudgeted_categories() {
+        let categories = [category(1, "store", false), category(2, "beer", false)];
+        let holder = RecordsHolder::new(&[record(&[(1, "80"), (2, "50")])], &categories, "EUR")
+            .with_budgets([(1, "100".parse().unwrap())].into_iter().collect());
+        assert_eq!(holder.budgeted_total(), "80".parse().unwrap());
+        assert_eq!(holder.all_total, "130".parse().unwrap());
+    }
+
+    #[test]
+    fn test_records_holder_converts_to_base_currency() {
+        let categories = [category(1, "store", false)];
+        let mut dollars = record(&[(1, "10.00")]);
+        dollars.currency = "USD".to_string();
+        dollars.rate = Some(0.925);
+        let mut yen = record(&[(1, "1000")]);
+        yen.currency = "JPY".to_string();
+        yen.rate = None;
+        let holder = RecordsHolder::new(&[record(&[(1, "1.00")]), dollars.clone(), yen.clone()], &categories, "EUR");
+        assert_eq!(holder.total(1), "10.25".parse().unwrap());
+        assert_eq!(holder.missing_rates, vec!["JPY".to_string()]);
+        assert_eq!(dollars.vec_of_fields(&categories, "EUR", Locale::Plain)[1..3], ["10.00 USD".to_string(), "9.25 (10.00 USD)".to_string()]);
+        assert_eq!(yen.vec_of_fields(&categories, "EUR", Locale::Plain)[2], "? (1000.00 JPY)");
+    }
+
+    #[test]
+    fn test_extract_tags() {
+        assert_eq!(extract_tags("#Holiday trip, #food. #ΦΑΓΗΤΟ #food #car-repair"), ["holiday", "food", "φαγητο", "car-repair"]);
+        assert!(extract_tags("no tags # here, c#").is_empty());
+    }
+
+    #[test]
+    fn test_fold_accents() {
+        assert_eq!(fold_accents("ΦΑΡΜΑΚΕΊΟ Προϊόντα Café"), "φαρμακειο προιοντα café");
+    }
+
+    #[test]
+    fn test_category_key() {
+        assert_eq!(category_key("Φαρμακείο"), "φαρμακείο");
+        assert_eq!(category_key("  Eating out! "), "eating-out");
+        assert_eq!(category_key("???"), "category");
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+/// An exact amount of money kept as integer minor units (cents).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+    /// Largest amount accepted from input, 10 billion. Far below `i64::MAX` so that
+    /// totals of millions of records can still be added without overflowing.
+    pub const MAX: Money = Money(1_000_000_000_000);
+
+    pub const fn from_cents(cents: i64) -> Self {
+        Money(cents)
+    }
+
+    pub const fn cents(self) -> i64 {
+        self.0
+    }
+
+    /// Only for display purposes such as chart bar heights, never for arithmetic.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    /// Multiplies by an exchange rate, rounding half away from zero to whole cents.
+    pub fn convert(self, rate: f64) -> Self {
+        Money((self.0 as f64 * rate).round() as i64)
+    }
+
+    /// `None` instead of overflowing, for sums of amounts typed by hand.
+    pub fn checked_add(self, rhs: Money) -> Option<Money> {
+        self.0.checked_add(rhs.0).map(Money)
+    }
+
+    pub fn checked_sub(self, rhs: Money) -> Option<Money> {
+        self.0.checked_sub(rhs.0).map(Money)
+    }
+
+    pub fn abs(self) -> Self {
+        Money(self.0.abs())
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the amount is within `±MAX`, the range every typed or parsed amount must be in.
+    pub fn in_range(self) -> bool {
+        self.0.unsigned_abs() <= Money::MAX.0.unsigned_abs()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMoneyError(String);
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid amount '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseMoneyError {}
+
+impl FromStr for Money {
+    type Err = ParseMoneyError;
+
+    /// Parses a plain decimal such as `12`, `-3.5` or `.99`. Digits past the
+    /// second decimal are rounded half away from zero.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseMoneyError(s.to_string());
+        let trimmed = s.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (int_part, frac_part) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(err());
+        }
+        if !int_part.bytes().chain(frac_part.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+
+        let units: i64 = if int_part.is_empty() { 0 } else { int_part.parse().map_err(|_| err())? };
+        let mut frac_digits = frac_part.bytes().map(|b| (b - b'0') as i64);
+        let tenths = frac_digits.next().unwrap_or(0);
+        let hundredths = frac_digits.next().unwrap_or(0);
+        let round_up = frac_digits.next().is_some_and(|d| d >= 5);
+
+        let cents = units
+            .checked_mul(100)
+            .and_then(|c| c.checked_add(tenths * 10 + hundredths + round_up as i64))
+            .ok_or_else(err)?;
+        Some(Money(if negative { -cents } else { cents })).filter(|m| m.in_range()).ok_or_else(err)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+impl ToSql for Money {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Money {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Money)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_money() {
+        assert_eq!("12".parse::<Money>().unwrap(), Money::from_cents(1200));
+        assert_eq!("12.5".parse::<Money>().unwrap(), Money::from_cents(1250));
+        assert_eq!("0.07".parse::<Money>().unwrap(), Money::from_cents(7));
+        assert_eq!(".99".parse::<Money>().unwrap(), Money::from_cents(99));
+        assert_eq!("-3.10".parse::<Money>().unwrap(), Money::from_cents(-310));
+        assert_eq!("1.115".parse::<Money>().unwrap(), Money::from_cents(112));
+        assert_eq!("1.114".parse::<Money>().unwrap(), Money::from_cents(111));
+        assert!("".parse::<Money>().is_err());
+        assert!(".".parse::<Money>().is_err());
+        assert!("1,5".parse::<Money>().is_err());
+        assert!("abc".parse::<Money>().is_err());
+        assert_eq!("-10000000000".parse::<Money>().unwrap(), -Money::MAX);
+        assert!("10000000000.01".parse::<Money>().is_err());
+    }
+
+    #[test]
+    fn test_display_money() {
+        assert_eq!(Money::from_cents(1250).to_string(), "12.50");
+        assert_eq!(Money::from_cents(7).to_string(), "0.07");
+        assert_eq!(Money::from_cents(-310).to_string(), "-3.10");
+        assert_eq!(Money::ZERO.to_string(), "0.00");
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(Money::from_cents(5).checked_add(Money::from_cents(7)), Some(Money::from_cents(12)));
+        assert_eq!(Money::from_cents(i64::MAX).checked_add(Money::from_cents(1)), None);
+        assert_eq!(Money::ZERO.checked_sub(Money::from_cents(i64::MIN)), None);
+    }
+
+    #[test]
+    fn test_sum_does_not_drift() {
+        let cents: Money = std::iter::repeat_n(Money::from_cents(10), 1000).sum();
+        assert_eq!(cents, Money::from_cents(10_000));
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use crate::error::{BuldakError, Result};
+use crate::model::first_of_month;
+
+/// How the tabs split time; cycled with `v`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodKind {
+    Month,
+    Quarter,
+    Year,
+}
+
+impl PeriodKind {
+    pub fn next(self) -> Self {
+        match self {
+            PeriodKind::Month => PeriodKind::Quarter,
+            PeriodKind::Quarter => PeriodKind::Year,
+            PeriodKind::Year => PeriodKind::Month,
+        }
+    }
+}
+
+/// A span of days shown by one tab. Calendar periods hold their first day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Month(NaiveDate),
+    Quarter(NaiveDate),
+    Year(NaiveDate),
+    /// Both ends included.
+    Custom { from: NaiveDate, to: NaiveDate },
+}
+
+impl Period {
+    /// The period of the given kind that contains `date`.
+    pub fn containing(kind: PeriodKind, date: NaiveDate) -> Self {
+        let month = first_of_month(date);
+        match kind {
+            PeriodKind::Month => Period::Month(month),
+            PeriodKind::Quarter => Period::Quarter(month.with_month((month.month0() / 3) * 3 + 1).unwrap()),
+            PeriodKind::Year => Period::Year(month.with_month(1).unwrap()),
+        }
+    }
+
+    /// First and last day, both included.
+    pub fn bounds(&self) -> (NaiveDate, NaiveDate) {
+        let (from, months) = match *self {
+            Period::Month(from) => (from, 1),
+            Period::Quarter(from) => (from, 3),
+            Period::Year(from) => (from, 12),
+            Period::Custom { from, to } => return (from, to),
+        };
+        (from, from + Months::new(months) - Days::new(1))
+    }
+
+    pub fn start(&self) -> NaiveDate {
+        self.bounds().0
+    }
+
+    /// First days of the months of a calendar period; custom ranges have no budget months.
+    pub fn budget_months(&self) -> Vec<NaiveDate> {
+        if let Period::Custom { .. } = self {
+            return vec![];
+        }
+        let (from, to) = self.bounds();
+        from.iter_days().take_while(|d| *d <= to).filter(|d| d.day() == 1).collect()
+    }
+
+    pub fn days(&self) -> i64 {
+        let (from, to) = self.bounds();
+        (to - from).num_days() + 1
+    }
+
+    /// Parses `2025-01-15..2025-03-31` into a custom range.
+    pub fn parse_range(input: &str) -> Result<Self> {
+        let invalid = || BuldakError::InvalidDate(input.to_string());
+        let (from, to) = input.split_once("..").ok_or_else(invalid)?;
+        let from: NaiveDate = from.trim().parse().map_err(|_| invalid())?;
+        let to: NaiveDate = to.trim().parse().map_err(|_| invalid())?;
+        if to < from {
+            return Err(invalid());
+        }
+        Ok(Period::Custom { from, to })
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Period::Month(from) => write!(f, "{}", from.format("%b-%Y")),
+            Period::Quarter(from) => write!(f, "Q{}-{}", from.month0() / 3 + 1, from.year()),
+            Period::Year(from) => write!(f, "{}", from.year()),
+            Period::Custom { from, to } => write!(f, "{}..{}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d")),
+        }
+    }
+}
+
+/// Distinct periods of `kind` covering the given months, keeping their order.
+pub fn periods_of(kind: PeriodKind, months: &[NaiveDate]) -> Vec<Period> {
+    let mut periods: Vec<Period> = Vec::new();
+    for month in months {
+        let period = Period::containing(kind, *month);
+        if !periods.contains(&period) {
+            periods.push(period);
+        }
+    }
+    periods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(Period::containing(PeriodKind::Month, d("2024-02-10")).bounds(), (d("2024-02-01"), d("2024-02-29")));
+        assert_eq!(Period::containing(PeriodKind::Quarter, d("2025-05-31")).bounds(), (d("2025-04-01"), d("2025-06-30")));
+        assert_eq!(Period::containing(PeriodKind::Year, d("2025-12-31")).bounds(), (d("2025-01-01"), d("2025-12-31")));
+        assert_eq!(Period::containing(PeriodKind::Quarter, d("2025-05-31")).to_string(), "Q2-2025");
+        assert_eq!(Period::containing(PeriodKind::Quarter, d("2025-05-31")).budget_months(), [d("2025-04-01"), d("2025-05-01"), d("2025-06-01")]);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(Period::parse_range("2025-01-15 .. 2025-03-31").unwrap().bounds(), (d("2025-01-15"), d("2025-03-31")));
+        assert!(Period::parse_range("2025-03-31..2025-01-15").is_err());
+        assert!(Period::parse_range("2025-01-15").is_err());
+    }
+
+    #[test]
+    fn test_periods_of_months() {
+        let months = [d("2025-05-01"), d("2025-04-01"), d("2025-03-01"), d("2024-12-01")];
+        let quarters = periods_of(PeriodKind::Quarter, &months);
+        assert_eq!(quarters.iter().map(Period::to_string).collect::<Vec<_>>(), ["Q2-2025", "Q1-2025", "Q4-2024"]);
+        assert_eq!(periods_of(PeriodKind::Year, &months).len(), 2);
+    }
+}
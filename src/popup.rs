@@ -0,0 +1,10 @@
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+
+/// Centered rectangle for modal popups, sized as a percentage of `area`.
+pub fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
+    let [area] = vertical.areas(area);
+    let [area] = horizontal.areas(area);
+    area
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use indexmap::IndexMap;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use crate::currency::normalize_currency;
+use crate::error::{BuldakError, Result};
+use crate::input_validator::convert_to_money;
+use crate::locale::Locale;
+use crate::model::{Category, Record};
+use crate::money::Money;
+
+/// When a recurring rule fires. Days past the end of a short month fall on its last day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// `monthly 15`
+    Monthly { day: u32 },
+    /// `weekly mon`
+    Weekly { weekday: Weekday },
+    /// `yearly 14/03`
+    Yearly { day: u32, month: u32 },
+}
+
+impl Schedule {
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        match *self {
+            Schedule::Monthly { day } => date.day() == day.min(last_day(date)),
+            Schedule::Weekly { weekday } => date.weekday() == weekday,
+            Schedule::Yearly { day, month } => date.month() == month && date.day() == day.min(last_day(date)),
+        }
+    }
+}
+
+fn last_day(date: NaiveDate) -> u32 {
+    let first = date.with_day(1).unwrap_or(date);
+    let next_month = first.checked_add_months(chrono::Months::new(1)).unwrap_or(first);
+    next_month.pred_opt().map_or(31, |d| d.day())
+}
+
+impl FromStr for Schedule {
+    type Err = BuldakError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || BuldakError::InvalidSchedule(s.to_string());
+        let (kind, value) = s.trim().split_once(' ').ok_or_else(invalid)?;
+        let value = value.trim();
+        match kind {
+            "monthly" => {
+                let day: u32 = value.parse().map_err(|_| invalid())?;
+                if !(1..=31).contains(&day) {
+                    return Err(invalid());
+                }
+                Ok(Schedule::Monthly { day })
+            }
+            "weekly" => Ok(Schedule::Weekly { weekday: value.parse().map_err(|_| invalid())? }),
+            "yearly" => {
+                let (day, month) = value.split_once('/').ok_or_else(invalid)?;
+                let day: u32 = day.parse().map_err(|_| invalid())?;
+                let month: u32 = month.parse().map_err(|_| invalid())?;
+                // 2024 is a leap year, so 29/02 is accepted
+                NaiveDate::from_ymd_opt(2024, month, day).ok_or_else(invalid)?;
+                Ok(Schedule::Yearly { day, month })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Monthly { day } => write!(f, "monthly {}", day),
+            Schedule::Weekly { weekday } => write!(f, "weekly {}", weekday.to_string().to_lowercase()),
+            Schedule::Yearly { day, month } => write!(f, "yearly {:02}/{:02}", day, month),
+        }
+    }
+}
+
+impl ToSql for Schedule {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for Schedule {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        String::column_result(value)?.parse().map_err(|err| FromSqlError::Other(Box::new(err)))
+    }
+}
+
+/// An expense that is entered automatically, e.g. rent on the 1st of every month.
+#[derive(Debug, Clone)]
+pub struct RecurringRule {
+    pub id: i64,
+    pub amounts: IndexMap<i64, Money>,
+    pub comments: String,
+    pub schedule: Schedule,
+    /// No entries are created before this day.
+    pub start_date: NaiveDate,
+    /// Last day that was materialized, `None` until the first run.
+    pub last_run: Option<NaiveDate>,
+    pub currency: String,
+}
+
+impl RecurringRule {
+    /// Days with an entry not yet created, up to and including `today`.
+    pub fn due_dates(&self, today: NaiveDate) -> Vec<NaiveDate> {
+        let from = self.last_run.and_then(|d| d.succ_opt()).unwrap_or(self.start_date).max(self.start_date);
+        from.iter_days()
+            .take_while(|d| *d <= today)
+            .filter(|d| self.schedule.matches(*d))
+            .collect()
+    }
+
+    /// The next `count` days this rule fires after `today`, for the preview.
+    pub fn upcoming(&self, today: NaiveDate, count: usize) -> Vec<NaiveDate> {
+        let from = today.max(self.last_run.unwrap_or(today)).max(self.start_date - Days::new(1));
+        from.iter_days()
+            .skip(1)
+            .take(366 * 2)
+            .filter(|d| self.schedule.matches(*d))
+            .take(count)
+            .collect()
+    }
+
+    pub fn record_on(&self, date: NaiveDate) -> Record {
+        Record {
+            id: 0,
+            amounts: self.amounts.clone(),
+            comments: self.comments.clone(),
+            date,
+            currency: self.currency.clone(),
+            rate: None,
+        }
+    }
+}
+
+/// Parses `store=450 beer=3+2` into amounts by category key. The currency is given as
+/// `currency=USD`; without it the amounts are in `default_currency`. A bare word is an
+/// error, so that a mistyped `gym` isn't taken for a currency.
+pub fn parse_amounts(input: &str, categories: &[Category], default_currency: &str, locale: Locale) -> Result<(IndexMap<i64, Money>, String)> {
+    let mut amounts = IndexMap::new();
+    let mut currency = default_currency.to_string();
+    for part in input.split_whitespace() {
+        let (key, amount) = part.split_once('=').ok_or_else(|| BuldakError::InvalidAmount {
+            input: part.to_string(),
+            reason: "expected category=amount or currency=CUR".to_string(),
+        })?;
+        if key == "currency" {
+            currency = normalize_currency(amount)?;
+            continue;
+        }
+        let category = categories.iter().find(|c| c.key == key)
+            .ok_or_else(|| BuldakError::UnknownCategory(key.to_string()))?;
+        *amounts.entry(category.id).or_default() += convert_to_money(amount, locale)?;
+    }
+    if amounts.values().all(|amount: &Money| amount.is_zero()) {
+        return Err(BuldakError::InvalidAmount { input: input.to_string(), reason: "no amount".to_string() });
+    }
+    Ok((amounts, currency))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::InMemoryRepository;
+    use crate::repository::Repository;
+
+    fn d(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn rule(schedule: &str, start: &str, last_run: Option<&str>) -> RecurringRule {
+        RecurringRule {
+            id: 1,
+            amounts: [(1, "450".parse().unwrap())].into_iter().collect(),
+            comments: "rent".to_string(),
+            schedule: schedule.parse().unwrap(),
+            start_date: d(start),
+            last_run: last_run.map(d),
+            currency: "EUR".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!("monthly 31".parse::<Schedule>().unwrap(), Schedule::Monthly { day: 31 });
+        assert_eq!("weekly mon".parse::<Schedule>().unwrap().to_string(), "weekly mon");
+        assert_eq!("yearly 29/2".parse::<Schedule>().unwrap().to_string(), "yearly 29/02");
+        assert!("monthly 32".parse::<Schedule>().is_err());
+        assert!("yearly 31/04".parse::<Schedule>().is_err());
+        assert!("daily 1".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn test_due_dates_since_last_run() {
+        let monthly = rule("monthly 31", "2025-01-01", Some("2025-01-31"));
+        assert_eq!(monthly.due_dates(d("2025-04-15")), vec![d("2025-02-28"), d("2025-03-31")]);
+        assert!(monthly.due_dates(d("2025-01-31")).is_empty());
+
+        let weekly = rule("weekly fri", "2025-03-05", None);
+        assert_eq!(weekly.due_dates(d("2025-03-14")), vec![d("2025-03-07"), d("2025-03-14")]);
+
+        let yearly = rule("yearly 29/02", "2023-01-01", None);
+        assert_eq!(yearly.due_dates(d("2025-03-01")), vec![d("2023-02-28"), d("2024-02-29"), d("2025-02-28")]);
+    }
+
+    #[test]
+    fn test_parse_amounts() {
+        let categories = InMemoryRepository::new().categories().unwrap();
+        let (amounts, currency) = parse_amounts("store=450 beer=3+2 store=10", &categories, "EUR", Locale::Plain).unwrap();
+        assert_eq!(amounts, [(1, "460".parse().unwrap()), (2, "5".parse().unwrap())].into_iter().collect::<IndexMap<_, _>>());
+        assert_eq!(currency, "EUR");
+        let (_, currency) = parse_amounts("currency=usd store=450", &categories, "EUR", Locale::Plain).unwrap();
+        assert_eq!(currency, "USD");
+
+        assert!(matches!(parse_amounts("gym store=450", &categories, "EUR", Locale::Plain), Err(BuldakError::InvalidAmount { .. })));
+        assert!(parse_amounts("store=450 currency=euro", &categories, "EUR", Locale::Plain).is_err());
+        assert!(matches!(parse_amounts("gym=450", &categories, "EUR", Locale::Plain), Err(BuldakError::UnknownCategory(_))));
+    }
+
+    #[test]
+    fn test_upcoming() {
+        let monthly = rule("monthly 1", "2025-01-01", Some("2025-03-18"));
+        assert_eq!(monthly.upcoming(d("2025-03-18"), 2), vec![d("2025-04-01"), d("2025-05-01")]);
+        let future = rule("monthly 1", "2025-06-01", None);
+        assert_eq!(future.upcoming(d("2025-03-18"), 1), vec![d("2025-06-01")]);
+    }
+}
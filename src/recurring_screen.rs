@@ -0,0 +1,235 @@
+use chrono::{NaiveDate, Utc};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tui_textarea::TextArea;
+use crate::currency::DEFAULT_CURRENCY;
+use crate::error::{BuldakError, Result};
+use crate::model::Category;
+use crate::popup::popup_area;
+use crate::locale::Locale;
+use crate::recurring::{parse_amounts, RecurringRule};
+use crate::repository::Repository;
+
+/// Titles of the form fields, in Tab order.
+const FIELDS: [&str; 4] = ["Πρόγραμμα (monthly 1 / weekly mon / yearly 14/03)", "Από", "Ποσά (store=450 beer=3 [currency=USD])", "Σχόλια"];
+const PREVIEW_COUNT: usize = 5;
+
+pub enum RecurringMode {
+    Browse,
+    Adding,
+}
+
+/// What the app should do after a key was handled by the recurring rules screen.
+pub enum RecurringAction {
+    None,
+    /// Rules changed and this many records were created for them.
+    Changed(usize),
+    Close,
+}
+
+pub struct RecurringState<'a> {
+    pub rules: Vec<RecurringRule>,
+    pub categories: Vec<Category>,
+    pub list_state: ListState,
+    pub mode: RecurringMode,
+    pub fields: Vec<TextArea<'a>>,
+    pub selected_field: usize,
+    /// Currency of new rules that don't name one.
+    pub base_currency: String,
+    pub locale: Locale,
+}
+
+impl RecurringState<'_> {
+    pub fn new() -> Self {
+        Self {
+            rules: vec![],
+            categories: vec![],
+            list_state: ListState::default(),
+            mode: RecurringMode::Browse,
+            fields: vec![],
+            selected_field: 0,
+            base_currency: DEFAULT_CURRENCY.to_string(),
+            locale: Locale::Plain,
+        }
+    }
+
+    pub fn reload(&mut self, repo: &dyn Repository) -> Result<()> {
+        self.rules = repo.recurring_rules()?;
+        self.categories = repo.categories()?;
+        self.base_currency = repo.base_currency()?;
+        self.locale = Locale::load(repo)?;
+        if self.list_state.selected().is_none_or(|i| i >= self.rules.len()) {
+            self.list_state.select(if self.rules.is_empty() { None } else { Some(0) });
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&RecurringRule> {
+        self.list_state.selected().and_then(|i| self.rules.get(i))
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<RecurringAction> {
+        match self.mode {
+            RecurringMode::Browse => self.on_browse_key(key, repo),
+            RecurringMode::Adding => self.on_form_key(key, repo),
+        }
+    }
+
+    fn on_browse_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<RecurringAction> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(RecurringAction::Close),
+            KeyCode::Down | KeyCode::Char('j') => self.list_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.list_state.select_previous(),
+            KeyCode::Char('a') => {
+                let today = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+                self.fields = FIELDS.iter().map(|_| TextArea::default()).collect();
+                self.fields[1] = TextArea::new(vec![today]);
+                self.selected_field = 0;
+                self.mode = RecurringMode::Adding;
+            }
+            KeyCode::Char('x') | KeyCode::Delete => {
+                if let Some(rule) = self.selected() {
+                    repo.delete_recurring_rule(rule.id)?;
+                    self.reload(repo)?;
+                    return Ok(RecurringAction::Changed(0));
+                }
+            }
+            _ => {}
+        }
+        Ok(RecurringAction::None)
+    }
+
+    fn on_form_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<RecurringAction> {
+        match key.code {
+            KeyCode::Esc => self.mode = RecurringMode::Browse,
+            KeyCode::Tab => self.selected_field = (self.selected_field + 1) % FIELDS.len(),
+            KeyCode::BackTab => self.selected_field = (self.selected_field + FIELDS.len() - 1) % FIELDS.len(),
+            KeyCode::Enter => {
+                // on error the form stays open so the user can fix it
+                let rule = self.draft()?;
+                repo.add_recurring_rule(&rule)?;
+                let created = repo.materialize_recurring(Utc::now().date_naive())?;
+                self.mode = RecurringMode::Browse;
+                self.reload(repo)?;
+                self.list_state.select(Some(self.rules.len().saturating_sub(1)));
+                return Ok(RecurringAction::Changed(created));
+            }
+            _ => {
+                self.fields[self.selected_field].input(key);
+            }
+        }
+        Ok(RecurringAction::None)
+    }
+
+    /// The rule typed in the form so far.
+    fn draft(&self) -> Result<RecurringRule> {
+        let field = |i: usize| self.fields[i].lines()[0].trim().to_string();
+        let start = field(1);
+        let schedule = field(0).parse()?;
+        let start_date = start.parse::<NaiveDate>().map_err(|_| BuldakError::InvalidDate(start))?;
+        let (amounts, currency) = parse_amounts(&field(2), &self.categories, &self.base_currency, self.locale)?;
+        Ok(RecurringRule { id: 0, amounts, comments: field(3), schedule, start_date, last_run: None, currency })
+    }
+
+    fn describe(&self, rule: &RecurringRule) -> String {
+        let amounts = rule.amounts.iter()
+            .map(|(id, amount)| {
+                let name = self.categories.iter().find(|c| c.id == *id).map_or("?", |c| c.name.as_str());
+                format!("{} {}", name, amount)
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("{:<18} {} {}  {}", rule.schedule.to_string(), amounts, rule.currency, rule.comments)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let area = popup_area(area, 70, 70);
+        frame.render_widget(Clear, area);
+        let form_height = match self.mode {
+            RecurringMode::Browse => 0,
+            RecurringMode::Adding => 3,
+        };
+        let [list_area, preview_area, form_area, help_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(PREVIEW_COUNT as u16 + 2),
+            Constraint::Length(form_height),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let items = self.rules.iter().map(|rule| ListItem::new(self.describe(rule)));
+        let list = List::new(items)
+            .block(Block::bordered().title("Επαναλαμβανόμενα έξοδα").border_style(Color::Green))
+            .style(Color::Green)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("+++> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        self.render_preview(frame, preview_area);
+        if let RecurringMode::Adding = self.mode {
+            self.render_form(frame, form_area);
+        }
+
+        let help = match self.mode {
+            RecurringMode::Browse => Line::from(vec![
+                "a".green().bold(), " νέο ".green(),
+                "x".green().bold(), " διαγραφή ".green(),
+                "Esc".green().bold(), " κλείσιμο".green(),
+            ]),
+            RecurringMode::Adding => Line::from(vec![
+                "Tab".yellow().bold(), " επόμενο πεδίο ".yellow(),
+                "Enter".yellow().bold(), " αποθήκευση ".yellow(),
+                "Esc".yellow().bold(), " ακύρωση".yellow(),
+            ]),
+        };
+        frame.render_widget(Paragraph::new(help).on_black(), help_area);
+    }
+
+    /// Next dates of the selected rule, or of the rule being typed once it is valid.
+    fn render_preview(&self, frame: &mut Frame, area: Rect) {
+        let today = Utc::now().date_naive();
+        let (rule, error) = match self.mode {
+            RecurringMode::Browse => (self.selected().cloned(), None),
+            RecurringMode::Adding => match self.draft() {
+                Ok(rule) => (Some(rule), None),
+                Err(err) => (None, Some(err.to_string())),
+            },
+        };
+        let lines: Vec<Line> = match (rule, error) {
+            (Some(rule), _) => rule.upcoming(today, PREVIEW_COUNT).iter()
+                .map(|date| Line::from(format!("{}  {}  {}", date.format("%Y-%m-%d"), self.locale.money(rule.record_on(*date).get_day_summary(), &rule.currency), rule.comments)))
+                .collect(),
+            (None, Some(error)) => vec![Line::from(error).red()],
+            (None, None) => vec![],
+        };
+        let preview = Paragraph::new(lines)
+            .block(Block::bordered().title("Επόμενες εγγραφές").border_style(Color::Green))
+            .style(Color::Green);
+        frame.render_widget(preview, area);
+    }
+
+    fn render_form(&mut self, frame: &mut Frame, area: Rect) {
+        let areas = Layout::horizontal([
+            Constraint::Percentage(30),
+            Constraint::Length(14),
+            Constraint::Percentage(30),
+            Constraint::Fill(1),
+        ])
+        .split(area);
+        for (i, (field, rect)) in self.fields.iter_mut().zip(areas.iter()).enumerate() {
+            let colour = if i == self.selected_field { Color::Yellow } else { Color::Green };
+            field.set_cursor_line_style(Style::default());
+            field.set_cursor_style(if i == self.selected_field {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            });
+            field.set_block(Block::default().borders(Borders::ALL).style(Style::default().fg(colour)).title(FIELDS[i]));
+            frame.render_widget(&*field, *rect);
+        }
+    }
+}
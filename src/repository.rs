@@ -0,0 +1,366 @@
+use std::fmt;
+use chrono::{NaiveDate, Utc};
+use indexmap::IndexMap;
+use crate::currency::ExchangeRate;
+use crate::error::Result;
+use crate::money::Money;
+use crate::model::{first_of_month, Category, Record, RecordFilter, RecordsHolder};
+use crate::period::Period;
+use crate::recurring::RecurringRule;
+
+/// Storage used by the UI. [`crate::db_repo::SqliteRepository`] is the real one,
+/// [`memory::InMemoryRepository`] lets tests drive the app without a database file.
+pub trait Repository: fmt::Debug {
+    /// Inserts a new record and returns its id.
+    fn save_record(&self, record: &Record) -> Result<i64>;
+    /// Inserts all the records or, when one fails, none of them.
+    fn save_records(&self, records: &[Record]) -> Result<Vec<i64>>;
+    /// Overwrites comment, date and all category lines of an existing record.
+    fn update_record(&self, record: &Record) -> Result<()>;
+    /// Soft delete, the record can come back with [`Repository::restore_record`].
+    fn delete_record(&self, id: i64) -> Result<()>;
+    fn restore_record(&self, id: i64) -> Result<()>;
+    /// Records dated `from..=to` that pass `filter`, oldest first.
+    fn records_between(&self, from: NaiveDate, to: NaiveDate, filter: &RecordFilter) -> Result<RecordsHolder>;
+    /// Unfiltered records of a tab.
+    fn records_for_period(&self, period: &Period) -> Result<RecordsHolder> {
+        let (from, to) = period.bounds();
+        self.records_between(from, to, &RecordFilter::default())
+    }
+    /// Records whose comment or tags have a word starting with each word of `query`,
+    /// newest first, from every month.
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<Record>>;
+    /// First days of the months that have records, newest first, always including the current one.
+    fn months(&self) -> Result<Vec<NaiveDate>>;
+    /// Spending per tag in the base currency over `from..=to`, largest first. A record with
+    /// several tags counts towards each of them; records without a rate are left out.
+    fn tag_totals(&self, from: NaiveDate, to: NaiveDate) -> Result<IndexMap<String, Money>> {
+        let mut totals: IndexMap<String, Money> = IndexMap::new();
+        for record in self.records_between(from, to, &RecordFilter::default())?.records {
+            let Some(total) = record.base_total() else { continue };
+            for tag in record.tags() {
+                *totals.entry(tag).or_default() += total;
+            }
+        }
+        totals.sort_by(|tag_a, a, tag_b, b| b.cmp(a).then_with(|| tag_a.cmp(tag_b)));
+        Ok(totals)
+    }
+
+    /// All categories in display order, archived included.
+    fn categories(&self) -> Result<Vec<Category>>;
+    fn add_category(&self, name: &str, colour: &str) -> Result<i64>;
+    fn rename_category(&self, id: i64, name: &str) -> Result<()>;
+    fn set_category_colour(&self, id: i64, colour: &str) -> Result<()>;
+    fn set_category_archived(&self, id: i64, archived: bool) -> Result<()>;
+    /// Swaps the sort order of two categories, used to move one up or down.
+    fn swap_category_order(&self, first: &Category, second: &Category) -> Result<()>;
+
+    /// Budget of every category for `month`: the one set for that month, or else the
+    /// latest one set before it. Zero budgets (switched off) are left out.
+    fn budgets_for_month(&self, month: NaiveDate) -> Result<IndexMap<i64, Money>>;
+    /// Sets the budget from `month` on, until another month sets a new one.
+    fn set_budget(&self, category_id: i64, month: NaiveDate, amount: Money) -> Result<()>;
+
+    /// Currency all totals are converted to.
+    fn base_currency(&self) -> Result<String>;
+    fn set_base_currency(&self, currency: &str) -> Result<()>;
+    /// Stored preference such as the CSV delimiter, `None` until it is set.
+    fn setting(&self, key: &str) -> Result<Option<String>>;
+    fn set_setting(&self, key: &str, value: &str) -> Result<()>;
+    fn exchange_rates(&self) -> Result<Vec<ExchangeRate>>;
+    /// Adds or replaces rates in one go, returns how many were written.
+    fn add_exchange_rates(&self, rates: &[ExchangeRate]) -> Result<usize>;
+
+    fn recurring_rules(&self) -> Result<Vec<RecurringRule>>;
+    fn add_recurring_rule(&self, rule: &RecurringRule) -> Result<i64>;
+    fn delete_recurring_rule(&self, id: i64) -> Result<()>;
+    /// Creates the records of every rule that fell due up to `today`, all or none.
+    /// Returns how many were created.
+    fn materialize_recurring(&self, today: NaiveDate) -> Result<usize>;
+}
+
+/// Puts the current month in front when it has no records yet, so it always gets a tab.
+pub fn with_current_month(mut months: Vec<NaiveDate>) -> Vec<NaiveDate> {
+    let current_month = first_of_month(Utc::now().date_naive());
+    if !months.contains(&current_month) {
+        months.insert(0, current_month);
+    }
+    months
+}
+
+#[cfg(test)]
+pub mod memory {
+    use std::cell::{Cell, RefCell};
+    use chrono::NaiveDate;
+    use indexmap::IndexMap;
+    use crate::money::Money;
+    use crate::error::{BuldakError, Result};
+    use crate::model::{category_key, first_of_month, fold_accents, Category, Record, RecordFilter, RecordsHolder};
+    use crate::currency::{ExchangeRate, Rates};
+    use crate::migrations::{SEED_BASE_CURRENCY, SEED_CATEGORIES};
+    use crate::recurring::RecurringRule;
+    use super::{with_current_month, Repository};
+
+    #[derive(Debug, Default)]
+    struct Store {
+        records: Vec<(Record, bool)>,
+        categories: Vec<Category>,
+        rules: Vec<RecurringRule>,
+        /// `(category id, first of month, amount)`
+        budgets: Vec<(i64, NaiveDate, Money)>,
+        base_currency: String,
+        settings: Vec<(String, String)>,
+        rates: Vec<ExchangeRate>,
+        next_id: i64,
+    }
+
+    /// Keeps everything in vectors; starts with the same categories as a fresh database.
+    #[derive(Debug)]
+    pub struct InMemoryRepository {
+        store: RefCell<Store>,
+        /// Number of month or record list reads, lets tests check what the UI caches.
+        pub reads: Cell<usize>,
+    }
+
+    impl InMemoryRepository {
+        pub fn new() -> Self {
+            let categories = SEED_CATEGORIES.iter()
+                .map(|seed| Category {
+                    id: seed.id,
+                    key: seed.key.to_string(),
+                    name: seed.name.to_string(),
+                    colour: seed.colour.to_string(),
+                    sort_order: seed.id,
+                    archived: false,
+                })
+                .collect();
+            InMemoryRepository { store: RefCell::new(Store {
+                records: vec![],
+                categories,
+                rules: vec![],
+                budgets: vec![],
+                base_currency: SEED_BASE_CURRENCY.to_string(),
+                settings: vec![],
+                rates: vec![],
+                next_id: 1,
+            }), reads: Cell::new(0) }
+        }
+
+        fn with_record<F: FnOnce(&mut (Record, bool))>(&self, id: i64, f: F) -> Result<()> {
+            let mut store = self.store.borrow_mut();
+            let entry = store.records.iter_mut().find(|(r, _)| r.id == id)
+                .ok_or_else(|| BuldakError::Db(rusqlite::Error::QueryReturnedNoRows))?;
+            f(entry);
+            Ok(())
+        }
+
+        fn with_category<F: FnOnce(&mut Category)>(&self, id: i64, f: F) -> Result<()> {
+            let mut store = self.store.borrow_mut();
+            let category = store.categories.iter_mut().find(|c| c.id == id)
+                .ok_or_else(|| BuldakError::Db(rusqlite::Error::QueryReturnedNoRows))?;
+            f(category);
+            Ok(())
+        }
+    }
+
+    impl Repository for InMemoryRepository {
+        fn save_record(&self, record: &Record) -> Result<i64> {
+            let mut store = self.store.borrow_mut();
+            let id = store.next_id;
+            store.next_id += 1;
+            let mut record = record.clone();
+            record.id = id;
+            record.amounts.retain(|_, amount| !amount.is_zero());
+            store.records.push((record, false));
+            Ok(id)
+        }
+
+        fn save_records(&self, records: &[Record]) -> Result<Vec<i64>> {
+            records.iter().map(|record| self.save_record(record)).collect()
+        }
+
+        fn update_record(&self, record: &Record) -> Result<()> {
+            let mut updated = record.clone();
+            updated.amounts.retain(|_, amount| !amount.is_zero());
+            self.with_record(record.id, |(stored, _)| *stored = updated)
+        }
+
+        fn delete_record(&self, id: i64) -> Result<()> {
+            self.with_record(id, |(_, deleted)| *deleted = true)
+        }
+
+        fn restore_record(&self, id: i64) -> Result<()> {
+            self.with_record(id, |(_, deleted)| *deleted = false)
+        }
+
+        fn records_between(&self, from: NaiveDate, to: NaiveDate, filter: &RecordFilter) -> Result<RecordsHolder> {
+            self.reads.set(self.reads.get() + 1);
+            let store = self.store.borrow();
+            let rates = Rates::new(&store.base_currency, &store.rates);
+            let mut records = store.records.iter()
+                .filter(|(r, deleted)| !deleted && r.date >= from && r.date <= to)
+                .map(|(r, _)| Record { rate: rates.rate(&r.currency, r.date), ..r.clone() })
+                .filter(|r| filter.matches(r))
+                .collect::<Vec<Record>>();
+            records.sort_by_key(|r| (r.date, r.id));
+            Ok(RecordsHolder::new(&records, &store.categories, &store.base_currency))
+        }
+
+        fn search(&self, query: &str, limit: usize) -> Result<Vec<Record>> {
+            let terms: Vec<String> = query.split_whitespace()
+                .map(|word| fold_accents(word.trim_start_matches('#')))
+                .filter(|word| !word.is_empty())
+                .collect();
+            if terms.is_empty() {
+                return Ok(vec![]);
+            }
+            let store = self.store.borrow();
+            let mut records = store.records.iter()
+                .filter(|(r, deleted)| !deleted && {
+                    let words: Vec<String> = fold_accents(&r.comments).split(|c: char| !c.is_alphanumeric())
+                        .map(str::to_string)
+                        .collect();
+                    terms.iter().all(|term| words.iter().any(|word| word.starts_with(term)))
+                })
+                .map(|(r, _)| r.clone())
+                .collect::<Vec<Record>>();
+            records.sort_by_key(|r| std::cmp::Reverse((r.date, r.id)));
+            records.truncate(limit);
+            Ok(records)
+        }
+
+        fn months(&self) -> Result<Vec<NaiveDate>> {
+            self.reads.set(self.reads.get() + 1);
+            let store = self.store.borrow();
+            let mut months = store.records.iter()
+                .filter(|(_, deleted)| !deleted)
+                .map(|(r, _)| first_of_month(r.date))
+                .collect::<Vec<NaiveDate>>();
+            months.sort_by(|a, b| b.cmp(a));
+            months.dedup();
+            Ok(with_current_month(months))
+        }
+
+        fn categories(&self) -> Result<Vec<Category>> {
+            let mut categories = self.store.borrow().categories.clone();
+            categories.sort_by_key(|c| (c.sort_order, c.id));
+            Ok(categories)
+        }
+
+        fn add_category(&self, name: &str, colour: &str) -> Result<i64> {
+            let mut store = self.store.borrow_mut();
+            let id = store.categories.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+            let sort_order = store.categories.iter().map(|c| c.sort_order).max().unwrap_or(0) + 1;
+            store.categories.push(Category {
+                id,
+                key: category_key(name),
+                name: name.trim().to_string(),
+                colour: colour.to_string(),
+                sort_order,
+                archived: false,
+            });
+            Ok(id)
+        }
+
+        fn rename_category(&self, id: i64, name: &str) -> Result<()> {
+            self.with_category(id, |c| c.name = name.trim().to_string())
+        }
+
+        fn set_category_colour(&self, id: i64, colour: &str) -> Result<()> {
+            self.with_category(id, |c| c.colour = colour.to_string())
+        }
+
+        fn set_category_archived(&self, id: i64, archived: bool) -> Result<()> {
+            self.with_category(id, |c| c.archived = archived)
+        }
+
+        fn swap_category_order(&self, first: &Category, second: &Category) -> Result<()> {
+            self.with_category(first.id, |c| c.sort_order = second.sort_order)?;
+            self.with_category(second.id, |c| c.sort_order = first.sort_order)
+        }
+
+        fn budgets_for_month(&self, month: NaiveDate) -> Result<IndexMap<i64, Money>> {
+            let store = self.store.borrow();
+            let mut budgets = store.budgets.iter()
+                .filter(|(_, from, _)| *from <= first_of_month(month))
+                .collect::<Vec<_>>();
+            budgets.sort_by_key(|(category_id, from, _)| (*category_id, *from));
+            let mut latest = IndexMap::new();
+            for (category_id, _, amount) in budgets {
+                latest.insert(*category_id, *amount);
+            }
+            latest.retain(|_, amount| !amount.is_zero());
+            Ok(latest)
+        }
+
+        fn set_budget(&self, category_id: i64, month: NaiveDate, amount: Money) -> Result<()> {
+            let mut store = self.store.borrow_mut();
+            let month = first_of_month(month);
+            store.budgets.retain(|(id, from, _)| !(*id == category_id && *from == month));
+            store.budgets.push((category_id, month, amount));
+            Ok(())
+        }
+
+        fn base_currency(&self) -> Result<String> {
+            Ok(self.store.borrow().base_currency.clone())
+        }
+
+        fn set_base_currency(&self, currency: &str) -> Result<()> {
+            self.store.borrow_mut().base_currency = currency.to_string();
+            Ok(())
+        }
+
+        fn setting(&self, key: &str) -> Result<Option<String>> {
+            Ok(self.store.borrow().settings.iter().find(|(k, _)| k == key).map(|(_, value)| value.clone()))
+        }
+
+        fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+            let mut store = self.store.borrow_mut();
+            store.settings.retain(|(k, _)| k != key);
+            store.settings.push((key.to_string(), value.to_string()));
+            Ok(())
+        }
+
+        fn exchange_rates(&self) -> Result<Vec<ExchangeRate>> {
+            Ok(self.store.borrow().rates.clone())
+        }
+
+        fn add_exchange_rates(&self, rates: &[ExchangeRate]) -> Result<usize> {
+            let mut store = self.store.borrow_mut();
+            for rate in rates {
+                store.rates.retain(|r| (r.date, &r.from, &r.to) != (rate.date, &rate.from, &rate.to));
+                store.rates.push(rate.clone());
+            }
+            Ok(rates.len())
+        }
+
+        fn recurring_rules(&self) -> Result<Vec<RecurringRule>> {
+            Ok(self.store.borrow().rules.clone())
+        }
+
+        fn add_recurring_rule(&self, rule: &RecurringRule) -> Result<i64> {
+            let mut store = self.store.borrow_mut();
+            let mut rule = rule.clone();
+            rule.id = store.rules.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+            store.rules.push(rule.clone());
+            Ok(rule.id)
+        }
+
+        fn delete_recurring_rule(&self, id: i64) -> Result<()> {
+            self.store.borrow_mut().rules.retain(|r| r.id != id);
+            Ok(())
+        }
+
+        fn materialize_recurring(&self, today: NaiveDate) -> Result<usize> {
+            let records: Vec<Record> = self.store.borrow().rules.iter()
+                .flat_map(|rule| rule.due_dates(today).into_iter().map(|date| rule.record_on(date)))
+                .collect();
+            // the records and the last runs are stored together, like the sqlite transaction
+            self.save_records(&records)?;
+            for rule in self.store.borrow_mut().rules.iter_mut() {
+                rule.last_run = Some(today);
+            }
+            Ok(records.len())
+        }
+    }
+}
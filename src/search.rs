@@ -0,0 +1,98 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tui_textarea::TextArea;
+use crate::error::Result;
+use crate::locale::Locale;
+use crate::model::Record;
+use crate::popup::popup_area;
+use crate::repository::Repository;
+
+/// More than fit on screen; narrowing the query is quicker than scrolling.
+const RESULT_LIMIT: usize = 100;
+
+/// What the app should do after a key was handled by the search prompt.
+pub enum SearchAction {
+    None,
+    /// Show this record in its month.
+    Jump(Record),
+    Close,
+}
+
+/// The `/` prompt, searching comments and tags of every month as the user types.
+pub struct SearchState<'a> {
+    pub input: TextArea<'a>,
+    pub results: Vec<Record>,
+    pub list_state: ListState,
+}
+
+impl SearchState<'_> {
+    pub fn new() -> Self {
+        Self { input: TextArea::default(), results: vec![], list_state: ListState::default() }
+    }
+
+    /// Starts over with an empty prompt.
+    pub fn clear(&mut self) {
+        self.input = TextArea::default();
+        self.results.clear();
+        self.list_state.select(None);
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent, repo: &dyn Repository) -> Result<SearchAction> {
+        match key.code {
+            KeyCode::Esc => return Ok(SearchAction::Close),
+            KeyCode::Down => self.list_state.select_next(),
+            KeyCode::Up => self.list_state.select_previous(),
+            KeyCode::Enter => {
+                if let Some(record) = self.list_state.selected().and_then(|i| self.results.get(i)) {
+                    return Ok(SearchAction::Jump(record.clone()));
+                }
+            }
+            _ => {
+                if self.input.input(key) {
+                    self.results = repo.search(&self.input.lines()[0], RESULT_LIMIT)?;
+                    self.list_state.select(if self.results.is_empty() { None } else { Some(0) });
+                }
+            }
+        }
+        Ok(SearchAction::None)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, locale: Locale) {
+        let area = popup_area(area, 70, 70);
+        frame.render_widget(Clear, area);
+        let [input_area, list_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        self.input.set_cursor_line_style(Style::default());
+        self.input.set_block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::Yellow)).title("Αναζήτηση σε σχόλια και ετικέτες"));
+        frame.render_widget(&self.input, input_area);
+
+        let items = self.results.iter().map(|record| ListItem::new(format!(
+            "{}  {:>14}  {}",
+            record.date.format("%Y-%m-%d"),
+            locale.money(record.get_day_summary(), &record.currency),
+            record.comments,
+        )));
+        let list = List::new(items)
+            .block(Block::bordered().title(format!("Αποτελέσματα: {}", self.results.len())).border_style(Color::Green))
+            .style(Color::Green)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("+++> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let help = Line::from(vec![
+            "↑↓".green().bold(), " επιλογή ".green(),
+            "Enter".green().bold(), " μετάβαση στην εγγραφή ".green(),
+            "Esc".green().bold(), " κλείσιμο".green(),
+        ]);
+        frame.render_widget(Paragraph::new(help).on_black(), help_area);
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use indexmap::IndexMap;
+use crate::money::Money;
+
+/// Why the negative amounts can't be taken out of the receipt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitError {
+    /// The parts are more than the receipt total they are taken out of.
+    Exceeds { total: Money, taken: Money },
+    /// The parts add up to more than an amount can hold.
+    TooLarge,
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::Exceeds { total, taken } => write!(f, "split of {} is more than the receipt total {}", taken, total),
+            SplitError::TooLarge => write!(f, "split amounts too large"),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+/// A receipt typed once and divided among categories: the first category with a positive
+/// amount holds the receipt total, and every negative amount in another category is that
+/// much of the receipt belonging there. `store 54.20, beer -12, other -5` keeps 37.20 in
+/// store and moves 12 to beer and 5 to other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Split {
+    /// Category id the receipt total was typed in.
+    pub from: i64,
+    pub total: Money,
+    /// What stays in the category the receipt was typed in.
+    pub rest: Money,
+    /// Category ids and the amounts moved to them, in display order.
+    pub parts: Vec<(i64, Money)>,
+}
+
+impl Split {
+    /// The split in `amounts`, `None` when nothing is taken out of a receipt. Amounts that
+    /// are all zero or negative are refunds and not a split.
+    pub fn find(amounts: &IndexMap<i64, Money>) -> Result<Option<Split>, SplitError> {
+        let Some((from, total)) = amounts.iter().find(|(_, amount)| **amount > Money::ZERO) else {
+            return Ok(None);
+        };
+        let parts = amounts.iter()
+            .filter(|(_, amount)| amount.is_negative())
+            .map(|(category_id, amount)| Money::ZERO.checked_sub(*amount).map(|part| (*category_id, part)))
+            .collect::<Option<Vec<(i64, Money)>>>()
+            .ok_or(SplitError::TooLarge)?;
+        if parts.is_empty() {
+            return Ok(None);
+        }
+        let taken = parts.iter()
+            .try_fold(Money::ZERO, |sum, (_, part)| sum.checked_add(*part))
+            .ok_or(SplitError::TooLarge)?;
+        if taken > *total {
+            return Err(SplitError::Exceeds { total: *total, taken });
+        }
+        Ok(Some(Split { from: *from, total: *total, rest: *total - taken, parts }))
+    }
+}
+
+/// The amounts with a split applied: the parts moved out of the receipt total.
+pub fn apply_split(mut amounts: IndexMap<i64, Money>) -> Result<IndexMap<i64, Money>, SplitError> {
+    if let Some(split) = Split::find(&amounts)? {
+        amounts[&split.from] = split.rest;
+        for (category_id, amount) in split.parts {
+            amounts[&category_id] = amount;
+        }
+    }
+    Ok(amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORE: i64 = 1;
+    const BEER: i64 = 2;
+    const OTHER: i64 = 3;
+
+    fn amounts(list: &[(i64, &str)]) -> IndexMap<i64, Money> {
+        list.iter().map(|(id, amount)| (*id, amount.parse().unwrap())).collect()
+    }
+
+    #[test]
+    fn test_receipt_divided_among_categories() {
+        let split = Split::find(&amounts(&[(STORE, "54.20"), (BEER, "-12"), (OTHER, "-5")])).unwrap().unwrap();
+        assert_eq!((split.from, split.rest), (STORE, "37.20".parse().unwrap()));
+        assert_eq!(apply_split(amounts(&[(STORE, "54.20"), (BEER, "-12"), (OTHER, "-5")])).unwrap(),
+            amounts(&[(STORE, "37.20"), (BEER, "12"), (OTHER, "5")]));
+
+        // any pair, the receipt needn't be in the first category
+        assert_eq!(apply_split(amounts(&[(STORE, "0"), (BEER, "-3"), (OTHER, "10")])).unwrap(),
+            amounts(&[(STORE, "0"), (BEER, "3"), (OTHER, "7")]));
+        // a positive amount after the receipt is left alone
+        assert_eq!(apply_split(amounts(&[(STORE, "20"), (BEER, "-5"), (OTHER, "3")])).unwrap(),
+            amounts(&[(STORE, "15"), (BEER, "5"), (OTHER, "3")]));
+        // the whole receipt may go elsewhere
+        assert_eq!(apply_split(amounts(&[(STORE, "10"), (BEER, "-10")])).unwrap(), amounts(&[(STORE, "0"), (BEER, "10")]));
+    }
+
+    #[test]
+    fn test_no_split_and_invalid_split() {
+        assert_eq!(Split::find(&amounts(&[(STORE, "12"), (BEER, "3")])), Ok(None));
+        assert_eq!(Split::find(&amounts(&[(STORE, "-12"), (BEER, "0")])), Ok(None), "a refund is not a split");
+        assert_eq!(Split::find(&amounts(&[(STORE, "10"), (BEER, "-8"), (OTHER, "-4")])),
+            Err(SplitError::Exceeds { total: "10".parse().unwrap(), taken: "12".parse().unwrap() }));
+
+        let huge = [(STORE, Money::from_cents(1)), (BEER, Money::from_cents(-i64::MAX)), (OTHER, Money::from_cents(-i64::MAX))];
+        assert_eq!(Split::find(&huge.into_iter().collect()), Err(SplitError::TooLarge));
+        let lowest = [(STORE, Money::from_cents(1)), (BEER, Money::from_cents(i64::MIN))];
+        assert_eq!(Split::find(&lowest.into_iter().collect()), Err(SplitError::TooLarge));
+    }
+}
@@ -4,33 +4,41 @@ use ratatui::Frame;
 use ratatui::layout::{Constraint, Rect};
 use ratatui::prelude::{Color, Style};
 use ratatui::style::Stylize;
-use ratatui::widgets::{Row, Table, TableState};
-use crate::db_repo::{get_records_holder, RecordsHolder};
+use ratatui::widgets::{Cell, Row, Table, TableState};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, Clear, Paragraph, Wrap};
+use crate::locale::Locale;
+use crate::model::{Record, RecordsHolder};
+use crate::budget::BudgetLevel;
+use crate::chart::temperature_style;
+use crate::money::Money;
+use crate::popup::popup_area;
 
-pub fn render_table(frame: &mut Frame, area: Rect, table_state: &mut TableState, date : NaiveDate) {
-    let header = Row::new(["Ημερομηνία", "Προϊόντα", "Μπύρα", "Αλλος", "Σύνολο σε μέρα","Σχόλια"])
+pub fn render_table(frame: &mut Frame, area: Rect, table_state: &mut TableState, response: &RecordsHolder, locale: Locale) {
+    let mut header_cells = vec!(Cell::from("Ημερομηνία"));
+    header_cells.extend(response.categories.iter().map(|c| Cell::from(c.name.clone()).fg(c.color())));
+    header_cells.push(Cell::from("Σύνολο σε μέρα"));
+    header_cells.push(Cell::from("Σχόλια"));
+    let header = Row::new(header_cells)
         .style(Style::new().bold())
         .bottom_margin(1);
-    let response = get_records_holder(date).unwrap_or_else(|_| RecordsHolder::new(&vec!()));
-    let rows = response.clone().records.iter().map(|r| Row::new(r.vec_of_fields())).collect::<Vec<Row>>();
+    let rows = response.records.iter().map(|r| Row::new(r.vec_of_fields(&response.categories, &response.base_currency, locale))).collect::<Vec<Row>>();
     let dayz_total = response.records.iter().map(|r| r.date).collect::<HashSet<NaiveDate>>();
-    let footer = Row::new([
-        format!("Ημέρεz : {}", dayz_total.len()),
-        format!("Προϊόντα: {:.2}", response.store_total),
-        format!("Μπύρα: {:.2}", response.beer_total),
-        format!("Αλλος: {:.2}", response.allos_total),
-        format!("Σύνολο: {:.2}", response.all_total),
-    ]);
+    let mut footer_cells = vec!(Cell::from(format!("Ημέρεz : {}", dayz_total.len())));
+    let base = response.base_currency.as_str();
+    footer_cells.extend(response.categories.iter().map(|c| {
+        let total = response.total(c.id);
+        budget_cell(format!("{}: {}", c.name, locale.cell(total, base, base)), total, response.budget(c.id), |m| locale.cell(m, base, base))
+    }));
+    let mut total_line = format!("Σύνολο: {}", locale.money(response.all_total, base));
+    if !response.missing_rates.is_empty() {
+        total_line.push_str(&format!(" (χωρίς ισοτιμία: {})", response.missing_rates.join(", ")));
+    }
+    footer_cells.push(budget_cell(total_line, response.budgeted_total(), response.total_budget(), |m| locale.money(m, base)));
+    let footer = Row::new(footer_cells).height(2);
 
-
-    let widths = [
-        Constraint::Percentage(10),
-        Constraint::Percentage(10),
-        Constraint::Percentage(10),
-        Constraint::Percentage(10),
-        Constraint::Percentage(10),
-        Constraint::Fill(1),
-    ];
+    let mut widths = vec![Constraint::Percentage(10); response.categories.len() + 2];
+    widths.push(Constraint::Fill(1));
     let table = Table::new(rows, widths)
         .header(header)
         .footer(footer.italic())
@@ -42,4 +50,34 @@ pub fn render_table(frame: &mut Frame, area: Rect, table_state: &mut TableState,
         .highlight_symbol("+++>   ");
 
     frame.render_stateful_widget(table, area, table_state);
-}
\ No newline at end of file
+}
+
+/// Footer total with what is left of the budget underneath, coloured by how close it is.
+fn budget_cell(spent_line: String, spent: Money, budget: Option<Money>, format: impl Fn(Money) -> String) -> Cell<'static> {
+    let Some(budget) = budget else {
+        return Cell::from(spent_line);
+    };
+    let style = temperature_style(BudgetLevel::of(spent, Some(budget)));
+    Cell::from(Text::from(vec![
+        Line::from(format!("{} / {}", spent_line, format(budget))),
+        Line::from(format!("υπόλοιπο {}", format(budget - spent))),
+    ])).style(style)
+}
+
+/// Modal asking whether the selected record should be deleted.
+pub fn render_delete_confirmation(frame: &mut Frame, record: &Record, locale: Locale) {
+    let area = popup_area(frame.area(), 40, 20);
+    let text = vec![
+        Line::from(format!("{}  Σύνολο: {}", record.date.format("%Y-%m-%d"), locale.money(record.get_day_summary(), &record.currency))),
+        Line::from(record.comments.clone()).italic(),
+        Line::from(""),
+        Line::from("Διαγραφή αυτής της εγγραφής; (y/n)").bold(),
+    ];
+    let popup = Paragraph::new(text)
+        .centered()
+        .wrap(Wrap { trim: true })
+        .block(Block::bordered().title("Διαγραφή").border_style(Color::Red))
+        .style(Color::LightRed);
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
@@ -3,43 +3,96 @@ use ratatui::prelude::{Stylize};
 use ratatui::style::{Color};
 use ratatui::widgets::{Block, Tabs};
 use crate::App;
-use crate::db_repo::{get_month_year_naive};
+use crate::model::first_of_month;
+use crate::period::{periods_of, Period, PeriodKind};
+use crate::repository::Repository;
+use crate::error::Result;
 
 
 pub struct TabsState {
+    /// Months that have records, newest first.
     pub months: Vec<NaiveDate>,
+    pub kind: PeriodKind,
+    /// Set while a custom range replaces the calendar tabs.
+    pub custom: Option<Period>,
+    pub periods: Vec<Period>,
     pub index: usize,
 }
 
 impl TabsState {
-    pub fn new(app: App) -> Self {
-        let months = get_months();
-        *app.current_month.lock().unwrap() = months[0].clone();
-        Self { months, index: 0 }
+    pub fn new(app: &App) -> Result<Self> {
+        let months = app.repo.months()?;
+        let mut state = Self { months, kind: PeriodKind::Month, custom: None, periods: vec![], index: 0 };
+        state.rebuild_periods();
+        *app.current_period.lock().unwrap() = state.periods[0];
+        Ok(state)
     }
 
-    pub fn select_next(&mut self, app: App) {
-        self.index = (self.index + 1) % self.months.len();
-        *app.current_month.lock().unwrap() = self.months[self.index].clone();
+    /// Re-reads the month list after a write, keeping the previous one when the database fails.
+    pub fn refresh(&mut self, repo: &dyn Repository) -> Result<()> {
+        let selected = self.periods.get(self.index).copied();
+        self.months = repo.months()?;
+        self.rebuild_periods();
+        self.index = selected
+            .and_then(|selected| self.periods.iter().position(|p| *p == selected))
+            .unwrap_or(self.index.min(self.periods.len() - 1));
+        Ok(())
     }
 
-    pub fn select_previous(&mut self, app: App) {
+    fn rebuild_periods(&mut self) {
+        self.periods = match self.custom {
+            Some(custom) => vec![custom],
+            None => periods_of(self.kind, &self.months),
+        };
+    }
+
+    /// Switches between month, quarter and year tabs, staying on the period around the current one.
+    pub fn cycle_kind(&mut self, app: &App) {
+        let start = app.current_period.lock().unwrap().start();
+        self.kind = if self.custom.take().is_some() { PeriodKind::Month } else { self.kind.next() };
+        self.rebuild_periods();
+        let current = Period::containing(self.kind, start);
+        self.index = self.periods.iter().position(|p| *p == current).unwrap_or(0);
+        *app.current_period.lock().unwrap() = self.periods[self.index];
+    }
+
+    /// Shows a single tab for the given range until the next [`TabsState::cycle_kind`].
+    pub fn set_custom(&mut self, period: Period, app: &App) {
+        self.custom = Some(period);
+        self.rebuild_periods();
+        self.index = 0;
+        *app.current_period.lock().unwrap() = period;
+    }
+
+    /// Switches to the month tabs and selects the month of `date`.
+    pub fn select_month(&mut self, date: NaiveDate, app: &App) {
+        self.custom = None;
+        self.kind = PeriodKind::Month;
+        self.rebuild_periods();
+        let month = Period::Month(first_of_month(date));
+        self.index = self.periods.iter().position(|p| *p == month).unwrap_or(0);
+        *app.current_period.lock().unwrap() = self.periods[self.index];
+    }
+
+    pub fn select_next(&mut self, app: &App) {
+        self.index = (self.index + 1) % self.periods.len();
+        *app.current_period.lock().unwrap() = self.periods[self.index];
+    }
+
+    pub fn select_previous(&mut self, app: &App) {
         if self.index > 0 {
             self.index -= 1;
         } else {
-            self.index = self.months.len() - 1;
+            self.index = self.periods.len() - 1;
         }
-        *app.current_month.lock().unwrap() = self.months[self.index].clone();
+        *app.current_period.lock().unwrap() = self.periods[self.index];
     }
 }
 
-pub fn render_tabs(tabs_state: &mut TabsState) -> Tabs<'static> {
+pub fn render_tabs(tabs_state: &TabsState) -> Tabs<'static> {
     let highlight_style = (Color::Black, Color::Yellow);
-    tabs_state.months = get_months();
 
-    let dates_str: Vec<String> = tabs_state.months.iter().map(|my|{
-        my.format("%b-%Y").to_string()
-    } ).collect();
+    let dates_str: Vec<String> = tabs_state.periods.iter().map(Period::to_string).collect();
 
     Tabs::new(dates_str)
         .green()
@@ -49,7 +102,3 @@ pub fn render_tabs(tabs_state: &mut TabsState) -> Tabs<'static> {
         .padding("++", "++")
         .divider(" ")
 }
-
-fn get_months() -> Vec<NaiveDate> {
-    get_month_year_naive().unwrap_or_else(|_| panic!("Unable to retrieve dates from DB"))
-}
\ No newline at end of file
@@ -0,0 +1,56 @@
+use crate::error::Result;
+use crate::model::Record;
+use crate::repository::Repository;
+
+/// A write made during this session that `u` can take back.
+#[derive(Debug, Clone)]
+pub enum UndoAction {
+    /// Record was soft deleted, undo clears its deletion mark.
+    Deleted(Record),
+    /// Record was overwritten, holds the version before the edit.
+    Edited(Record),
+}
+
+impl UndoAction {
+    pub fn undo(&self, repo: &dyn Repository) -> Result<()> {
+        match self {
+            UndoAction::Deleted(record) => repo.restore_record(record.id)?,
+            UndoAction::Edited(previous) => repo.update_record(previous)?,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::model::RecordFilter;
+    use crate::repository::memory::InMemoryRepository;
+
+    #[test]
+    fn test_undo_delete_and_edit() {
+        let repo = InMemoryRepository::new();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 2).unwrap();
+        repo.save_record(&Record {
+            id: 0,
+            amounts: [(1, "10.50".parse().unwrap())].into_iter().collect(),
+            comments: "περίπτερο".to_string(),
+            date,
+            currency: "EUR".to_string(),
+            rate: None,
+        }).unwrap();
+        let record = repo.records_between(date, date, &RecordFilter::default()).unwrap().records[0].clone();
+
+        repo.delete_record(record.id).unwrap();
+        assert!(repo.records_between(date, date, &RecordFilter::default()).unwrap().records.is_empty());
+        UndoAction::Deleted(record.clone()).undo(&repo).unwrap();
+        assert_eq!(repo.records_between(date, date, &RecordFilter::default()).unwrap().records[0].amount(1), "10.50".parse().unwrap());
+
+        let mut edited = record.clone();
+        edited.comments = "σούπερ μάρκετ".to_string();
+        repo.update_record(&edited).unwrap();
+        UndoAction::Edited(record).undo(&repo).unwrap();
+        assert_eq!(repo.records_between(date, date, &RecordFilter::default()).unwrap().records[0].comments, "περίπτερο");
+    }
+}
@@ -0,0 +1,36 @@
+use indexmap::IndexMap;
+use crate::currency::DEFAULT_CURRENCY;
+use crate::error::Result;
+use crate::money::Money;
+use crate::model::{RecordFilter, RecordsHolder};
+use crate::period::Period;
+use crate::repository::Repository;
+
+/// Records of the tab on screen, kept between frames so drawing never touches the database.
+#[derive(Debug, Clone)]
+pub struct PeriodView {
+    pub period: Period,
+    pub filter: RecordFilter,
+    pub holder: RecordsHolder,
+}
+
+impl PeriodView {
+    pub fn empty(period: Period) -> Self {
+        PeriodView { period, filter: RecordFilter::default(), holder: RecordsHolder::new(&[], &[], DEFAULT_CURRENCY) }
+    }
+
+    /// Loads the records of `period` that pass `filter` and adds up the monthly budgets of the
+    /// months it covers. Budgets are per category, so a filtered view doesn't get them.
+    pub fn load(repo: &dyn Repository, period: Period, filter: &RecordFilter) -> Result<Self> {
+        let mut budgets: IndexMap<i64, Money> = IndexMap::new();
+        let months = if *filter == RecordFilter::default() { period.budget_months() } else { vec![] };
+        for month in months {
+            for (category_id, amount) in repo.budgets_for_month(month)? {
+                *budgets.entry(category_id).or_default() += amount;
+            }
+        }
+        let (from, to) = period.bounds();
+        let holder = repo.records_between(from, to, filter)?.with_budgets(budgets);
+        Ok(PeriodView { period, filter: filter.clone(), holder })
+    }
+}